@@ -0,0 +1,137 @@
+// SpatialHashの問い合わせと、全員と比べる場合の速さを比べる
+// cargo bench --bench spatial で実行する
+use cave::spatial::SpatialHash;
+use cave::vector::Vec2;
+use sdl2::rect::Rect;
+use specs::prelude::*;
+use std::time::{Duration, Instant};
+
+const WORLD_SIZE: i32 = 4096;
+const ENTITY_COUNTS: [usize; 3] = [1000, 5000, 20000];
+const QUERIES: usize = 1000;
+const QUERY_RADIUS: f64 = 48.0;
+
+// 毎回同じ配置になるように、乱数は自前の線形合同法で作る
+struct Lcg(u64);
+
+impl Lcg {
+  fn next(&mut self, max: i32) -> i32 {
+    self.0 = self
+      .0
+      .wrapping_mul(6_364_136_223_846_793_005)
+      .wrapping_add(1_442_695_040_888_963_407);
+    ((self.0 >> 33) % max as u64) as i32
+  }
+}
+
+fn time<F: FnMut() -> usize>(label: &str, repeats: u32, mut f: F) {
+  let mut found = 0;
+  let start = Instant::now();
+  for _ in 0..repeats {
+    found += f();
+  }
+  let per_run = start.elapsed() / repeats;
+  println!(
+    "  {:<28} {:>10.3} ms  (found {})",
+    label,
+    as_millis(per_run),
+    found / repeats as usize
+  );
+}
+
+fn as_millis(duration: Duration) -> f64 {
+  duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+  let mut world = World::new();
+  let mut rng = Lcg(0x5eed);
+
+  for &count in &ENTITY_COUNTS {
+    let entries: Vec<(Entity, Rect)> = (0..count)
+      .map(|_| {
+        let rect = Rect::new(
+          rng.next(WORLD_SIZE),
+          rng.next(WORLD_SIZE),
+          8 + rng.next(24) as u32,
+          8 + rng.next(24) as u32,
+        );
+        (world.create_entity().build(), rect)
+      })
+      .collect();
+    let centers: Vec<Vec2> = (0..QUERIES)
+      .map(|_| {
+        Vec2::new(
+          f64::from(rng.next(WORLD_SIZE)),
+          f64::from(rng.next(WORLD_SIZE)),
+        )
+      })
+      .collect();
+
+    println!("{} entities, {} queries", count, QUERIES);
+    let mut index = SpatialHash::default();
+    time("rebuild", 20, || {
+      index.clear();
+      for &(entity, rect) in &entries {
+        index.insert(entity, rect);
+      }
+      index.len()
+    });
+
+    time("query_radius", 20, || {
+      centers
+        .iter()
+        .map(|&center| index.query_radius(center, QUERY_RADIUS).len())
+        .sum()
+    });
+
+    time("query_radius (brute force)", 5, || {
+      centers
+        .iter()
+        .map(|&center| {
+          entries
+            .iter()
+            .filter(|(_, rect)| {
+              let nearest = Vec2::new(
+                center
+                  .x
+                  .max(f64::from(rect.left()))
+                  .min(f64::from(rect.right())),
+                center
+                  .y
+                  .max(f64::from(rect.top()))
+                  .min(f64::from(rect.bottom())),
+              );
+              center.distance(nearest) <= QUERY_RADIUS
+            })
+            .count()
+        })
+        .sum()
+    });
+
+    time("query_rect", 20, || {
+      centers
+        .iter()
+        .map(|&center| {
+          let rect = Rect::from_center(center.to_point(), 96, 96);
+          index.query_rect(rect).len()
+        })
+        .sum()
+    });
+
+    time("raycast", 20, || {
+      centers
+        .windows(2)
+        .filter(|pair| {
+          let direction = pair[1] - pair[0];
+          index
+            .raycast(pair[0], direction, direction.length(), |_| true)
+            .is_some()
+        })
+        .count()
+    });
+
+    world.delete_all();
+    world.maintain();
+  }
+}
@@ -0,0 +1,224 @@
+use sdl2::rect::Rect;
+use serde::Deserialize;
+use specs::prelude::*;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::components::*;
+use crate::pathfinding::{find_path, line_of_sight};
+use crate::tilemap::TileMap;
+use crate::time::DeltaTime;
+use crate::vector::Vec2;
+
+// 目的地やタイルの中心にこれだけ近づいたら着いたことにする(ピクセル)
+const ARRIVE_DISTANCE: f64 = 6.0;
+// うろつく先を選び直す回数。壁ばかりの場所で選び続けないようにする
+const WANDER_ATTEMPTS: usize = 8;
+// うろつくのをあきらめるまでの秒数。他の敵とぶつかって進めないときなどのため
+const WANDER_TIMEOUT: f64 = 5.0;
+
+// 敵の性格を決める値。設定ファイル(RON)から読み込む。例はassets/enemy_behavior.ronを参照
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Behavior {
+  // プレイヤーに気づく距離(ピクセル)。壁の向こうは見えない
+  pub sight_range: f64,
+  // 飛びかかり始める距離
+  pub attack_range: f64,
+  // プレイヤーがこれより近いと逃げる。0なら逃げない
+  #[serde(default)]
+  pub flee_range: f64,
+  // 追いかけるとき、うろつくときの速さ。MovementTuningのmax_speedに対する割合
+  pub chase_speed: f64,
+  pub wander_speed: f64,
+  // 最初にいた場所からこの距離の中をうろつく
+  pub wander_radius: f64,
+  // うろつく合間に立ち止まる秒数の目安
+  pub idle_time: f64,
+  // 見失ってから、最後に見た場所を目指し続ける秒数
+  pub memory: f64,
+  // 飛びかかっている秒数と、次に飛びかかれるようになるまでの秒数
+  pub attack_time: f64,
+  pub attack_cooldown: f64,
+  // 経路を探し直す間隔(秒)
+  pub repath_interval: f64,
+}
+
+impl Behavior {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    ron::de::from_str(source).map_err(|e| e.to_string())
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+}
+
+// Aiを持つエンティティの状態を進めて、プレイヤーと同じようにMovementのdesiredを決める
+// 動かすのはPhysics、向きに合わせたアニメーションはAnimatorに任せる
+pub struct EnemyAi;
+
+impl<'a> System<'a> for EnemyAi {
+  type SystemData = (
+    Read<'a, DeltaTime>,
+    ReadExpect<'a, TileMap>,
+    ReadStorage<'a, InputControlled>,
+    ReadStorage<'a, Position>,
+    WriteStorage<'a, Ai>,
+    WriteStorage<'a, Movement>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let dt = (data.0).0;
+    let map = &*data.1;
+    let players: Vec<Vec2> = (&data.2, &data.3).join().map(|(_, pos)| pos.0).collect();
+
+    for (pos, ai, movement) in (&data.3, &mut data.4, &mut data.5).join() {
+      let pos = pos.0;
+      // 見えているプレイヤーのうち一番近い者を狙う
+      let seen = players
+        .iter()
+        .copied()
+        .filter(|&player| pos.distance(player) <= ai.behavior.sight_range)
+        .filter(|&player| line_of_sight(map, pos, player))
+        .min_by(|a, b| pos.distance(*a).total_cmp(&pos.distance(*b)));
+
+      ai.cooldown = (ai.cooldown - dt).max(0.0);
+      ai.repath_timer -= dt;
+      let state = next_state(ai, pos, seen, dt, map);
+      // 状態が変わったら、前の状態で探した経路は捨てる
+      if std::mem::discriminant(&state) != std::mem::discriminant(&ai.state) {
+        ai.path.clear();
+        ai.repath_timer = 0.0;
+      }
+      ai.state = state;
+      movement.desired = desired(ai, pos, map);
+    }
+  }
+}
+
+fn next_state(ai: &mut Ai, pos: Vec2, seen: Option<Vec2>, dt: f64, map: &TileMap) -> AiState {
+  let behavior = ai.behavior;
+  // 飛びかかっている途中は、終わるまで続ける
+  if let AiState::Attack { direction, timer } = ai.state {
+    if timer > dt {
+      return AiState::Attack {
+        direction,
+        timer: timer - dt,
+      };
+    }
+    ai.cooldown = behavior.attack_cooldown;
+  }
+
+  if let Some(player) = seen {
+    let distance = pos.distance(player);
+    return if distance < behavior.flee_range {
+      AiState::Flee { threat: player }
+    } else if distance <= behavior.attack_range && ai.cooldown <= 0.0 {
+      AiState::Attack {
+        direction: (player - pos).normalized(),
+        timer: behavior.attack_time,
+      }
+    } else {
+      AiState::Chase {
+        last_seen: player,
+        memory: behavior.memory,
+      }
+    };
+  }
+
+  match ai.state {
+    AiState::Chase { last_seen, memory }
+      if memory > dt && pos.distance(last_seen) > ARRIVE_DISTANCE =>
+    {
+      AiState::Chase {
+        last_seen,
+        memory: memory - dt,
+      }
+    }
+    AiState::Idle { timer } if timer > dt => AiState::Idle { timer: timer - dt },
+    AiState::Idle { .. } => AiState::Wander {
+      goal: wander_goal(ai, map),
+      timer: WANDER_TIMEOUT,
+    },
+    AiState::Wander { goal, timer } if timer > dt && pos.distance(goal) > ARRIVE_DISTANCE => {
+      AiState::Wander {
+        goal,
+        timer: timer - dt,
+      }
+    }
+    // 見失った、逃げ切った、うろつき終わったときは、少し立ち止まる
+    _ => AiState::Idle {
+      timer: behavior.idle_time * (0.5 + ai.rng.next_f64()),
+    },
+  }
+}
+
+fn desired(ai: &mut Ai, pos: Vec2, map: &TileMap) -> Vec2 {
+  match ai.state {
+    AiState::Idle { .. } => Vec2::ZERO,
+    AiState::Wander { goal, .. } => follow(ai, pos, goal, map) * ai.behavior.wander_speed,
+    AiState::Chase { last_seen, .. } => follow(ai, pos, last_seen, map) * ai.behavior.chase_speed,
+    AiState::Flee { threat } => (pos - threat).normalized(),
+    AiState::Attack { direction, .. } => direction,
+  }
+}
+
+// goalへ向かう経路の、次に目指す点への向き
+fn follow(ai: &mut Ai, pos: Vec2, goal: Vec2, map: &TileMap) -> Vec2 {
+  // 見通せるなら、経路を探さずにまっすぐ向かう
+  if line_of_sight(map, pos, goal) {
+    ai.path.clear();
+    return (goal - pos).normalized();
+  }
+  if ai.repath_timer <= 0.0 {
+    ai.repath_timer = ai.behavior.repath_interval;
+    ai.path = plan(map, pos, goal);
+  }
+  while let Some(&next) = ai.path.last() {
+    if pos.distance(next) > ARRIVE_DISTANCE {
+      break;
+    }
+    ai.path.pop();
+  }
+  // 経路が見つからなかったときは、まっすぐ向かって壁に沿って滑らせる
+  let next = ai.path.last().copied().unwrap_or(goal);
+  (next - pos).normalized()
+}
+
+// fromからtoまでに通るタイルの中心を、最後に通る点から順に並べる
+fn plan(map: &TileMap, from: Vec2, to: Vec2) -> Vec<Vec2> {
+  let start = map.tile_at(from.to_point());
+  let goal = map.tile_at(to.to_point());
+  let mut path: Vec<Vec2> = match find_path(map, start, goal) {
+    Some(tiles) => tiles
+      .into_iter()
+      .map(|(x, y)| map.tile_rect(x, y).center().into())
+      .collect(),
+    None => return Vec::new(),
+  };
+  // 最後はタイルの中心ではなく、目的地そのものを目指す
+  if let Some(last) = path.last_mut() {
+    *last = to;
+  }
+  path.reverse();
+  path
+}
+
+// homeからwander_radiusの中で、壁でない場所を選ぶ。見つからなければhome
+fn wander_goal(ai: &mut Ai, map: &TileMap) -> Vec2 {
+  for _ in 0..WANDER_ATTEMPTS {
+    let angle = ai.rng.next_f64() * 2.0 * PI;
+    // 円の中に一様に散らばるように、半径は平方根をとる
+    let radius = ai.behavior.wander_radius * ai.rng.next_f64().sqrt();
+    let goal = ai.home + Vec2::new(angle.cos(), angle.sin()) * radius;
+    let point = goal.to_point();
+    let area = Rect::new(point.x(), point.y(), 1, 1);
+    if !map.tiles_in(area).any(|(x, y)| map.is_solid(x, y)) {
+      return goal;
+    }
+  }
+  ai.home
+}
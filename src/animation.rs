@@ -0,0 +1,136 @@
+use sdl2::rect::Rect;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::assets::{AssetManager, TextureHandle};
+use crate::components::*;
+
+// アニメーション定義ファイル(RON)の中身
+// 例はassets/seeker.ronを参照
+#[derive(Debug, Deserialize)]
+pub struct AnimationDef {
+  // フレームを切り出すスプライトシートのパス
+  pub spritesheet: String,
+  pub clips: HashMap<String, ClipDef>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClipDef {
+  #[serde(default = "default_mode")]
+  pub mode: LoopMode,
+  #[serde(default)]
+  pub frames: Vec<FrameDef>,
+  // 別のクリップを左右反転して使う。指定したときはmodeとframesは使わない
+  #[serde(default)]
+  pub mirror: Option<String>,
+}
+
+fn default_mode() -> LoopMode {
+  LoopMode::Loop
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FrameDef {
+  // スプライトシートの中の(x, y, 幅, 高さ)
+  pub region: (i32, i32, u32, u32),
+  // 表示しておく秒数
+  pub duration: f64,
+  #[serde(default)]
+  pub flip: Flip,
+}
+
+impl AnimationDef {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    ron::de::from_str(source).map_err(|e| e.to_string())
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  // spritesheetには、このファイルのスプライトシートを読み込んだハンドルを渡す
+  pub fn clip(&self, name: &str, spritesheet: TextureHandle) -> Result<AnimationClip, String> {
+    let def = self
+      .clips
+      .get(name)
+      .ok_or_else(|| format!("animation clip {:?} is not defined", name))?;
+    if let Some(source) = &def.mirror {
+      // 反転したクリップをさらに反転することはできない
+      if self
+        .clips
+        .get(source)
+        .is_some_and(|def| def.mirror.is_some())
+      {
+        return Err(format!("animation clip {:?} mirrors another mirror", name));
+      }
+      let mut clip = self.clip(source, spritesheet)?;
+      for frame in &mut clip.frames {
+        frame.sprite.flip.horizontal = !frame.sprite.flip.horizontal;
+      }
+      return Ok(clip);
+    }
+    if def.frames.is_empty() {
+      return Err(format!("animation clip {:?} has no frames", name));
+    }
+
+    let frames = def
+      .frames
+      .iter()
+      .map(|frame| {
+        let (x, y, width, height) = frame.region;
+        AnimationFrame {
+          sprite: Sprite {
+            spritesheet,
+            region: Rect::new(x, y, width, height),
+            flip: frame.flip,
+          },
+          duration: frame.duration,
+        }
+      })
+      .collect();
+
+    Ok(AnimationClip {
+      frames,
+      mode: def.mode,
+    })
+  }
+
+  // prefixに上下左右の向きをつけた4つのクリップをまとめて取り出す
+  fn directional_clips(
+    &self,
+    prefix: &str,
+    spritesheet: TextureHandle,
+  ) -> Result<DirectionalClips, String> {
+    Ok(DirectionalClips {
+      up: self.clip(&format!("{}up", prefix), spritesheet)?,
+      down: self.clip(&format!("{}down", prefix), spritesheet)?,
+      left: self.clip(&format!("{}left", prefix), spritesheet)?,
+      right: self.clip(&format!("{}right", prefix), spritesheet)?,
+    })
+  }
+
+  // up / down / left / rightの4つのクリップから歩行アニメーションを作る
+  // idle_up / idle_down / idle_left / idle_rightがあれば止まっているときに使う
+  pub fn movement_animation(&self, assets: &mut AssetManager) -> Result<MovementAnimation, String> {
+    let spritesheet = assets.load(&self.spritesheet)?;
+    let has_idle = self.clips.keys().any(|name| name.starts_with("idle_"));
+    let idle = if has_idle {
+      Some(self.directional_clips("idle_", spritesheet)?)
+    } else {
+      None
+    };
+
+    Ok(MovementAnimation {
+      current_frame: 0,
+      elapsed: 0.0,
+      facing: Direction::Down,
+      moving: false,
+      walk: self.directional_clips("", spritesheet)?,
+      idle,
+    })
+  }
+}
@@ -1,33 +1,64 @@
 use specs::prelude::*;
 
 use crate::components::*;
+use crate::time::DeltaTime;
 
 pub struct Animator;
 
 impl<'a> System<'a> for Animator {
   type SystemData = (
+    Read<'a, DeltaTime>,
     WriteStorage<'a, MovementAnimation>,
     WriteStorage<'a, Sprite>,
     ReadStorage<'a, Velocity>,
   );
 
   fn run(&mut self, mut data: Self::SystemData) {
-    use self::Direction::*;
+    let dt = (data.0).0;
 
-    for (anim, sprite, vel) in (&mut data.0, &mut data.1, &data.2).join() {
-      if vel.speed == 0 {
-        continue;
+    for (anim, sprite, vel) in (&mut data.1, &mut data.2, &data.3).join() {
+      // クリップは上下左右の4つなので、速度に一番近い上下左右の向きを使う
+      // 止まっているときは最後に向いていた方向のまま
+      let direction = vel.direction(false);
+      let moving = direction.is_some();
+      let facing = direction.unwrap_or(anim.facing);
+
+      // 向きが変わったり、歩き出したり止まったりしたらクリップを最初から再生する
+      if anim.facing != facing || anim.moving != moving {
+        anim.facing = facing;
+        anim.moving = moving;
+        anim.current_frame = 0;
+        anim.elapsed = 0.0;
+      } else {
+        anim.elapsed += dt;
       }
 
-      let frames = match vel.direction {
-        Left => &anim.left_frames,
-        Right => &anim.right_frames,
-        Up => &anim.up_frames,
-        Down => &anim.down_frames,
+      let clip = match (&anim.idle, moving) {
+        (_, true) => anim.walk.get(facing),
+        (Some(idle), false) => idle.get(facing),
+        (None, false) => {
+          *sprite = anim.walk.get(facing).frames[0].sprite.clone();
+          continue;
+        }
       };
 
-      anim.current_frame = (anim.current_frame + 1) % frames.len();
-      *sprite = frames[anim.current_frame].clone();
+      loop {
+        let duration = clip.frames[anim.current_frame].duration;
+        if duration <= 0.0 || anim.elapsed < duration {
+          break;
+        }
+
+        let next_frame = clip.next_frame(anim.current_frame);
+        if next_frame == anim.current_frame {
+          // 最後のフレームで止まるクリップは、それ以上時間を貯めない
+          anim.elapsed = 0.0;
+          break;
+        }
+        anim.elapsed -= duration;
+        anim.current_frame = next_frame;
+      }
+
+      *sprite = clip.frames[anim.current_frame].sprite.clone();
     }
   }
 }
@@ -0,0 +1,170 @@
+use sdl2::image::LoadTexture;
+use sdl2::render::{Texture, TextureCreator};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// AssetManagerが配るテクスチャの番号。Spriteなどはこれでスプライトシートを指す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TextureHandle(usize);
+
+#[derive(Debug)]
+pub enum AssetError {
+  NotFound(PathBuf),
+  Load { path: PathBuf, message: String },
+}
+
+impl fmt::Display for AssetError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AssetError::NotFound(path) => write!(f, "{}: file not found", path.display()),
+      AssetError::Load { path, message } => write!(f, "{}: {}", path.display(), message),
+    }
+  }
+}
+
+impl Error for AssetError {}
+
+// このクレートのローダーはStringでエラーを返すので、?でそのまま渡せるようにする
+impl From<AssetError> for String {
+  fn from(e: AssetError) -> Self {
+    e.to_string()
+  }
+}
+
+// テクスチャのパスとハンドルの対応を表すリソース
+// 同じファイルを何度loadしても同じハンドルを返す。SDLに依存しないのでヘッドレスでも使える
+#[derive(Debug, Default)]
+pub struct AssetManager {
+  paths: Vec<PathBuf>,
+  handles: HashMap<PathBuf, TextureHandle>,
+}
+
+impl AssetManager {
+  pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<TextureHandle, AssetError> {
+    let path = path.as_ref();
+    // "assets/./seeker.png"と"assets/seeker.png"を同じファイルとして扱う
+    let key = fs::canonicalize(path).map_err(|_| AssetError::NotFound(path.to_path_buf()))?;
+    if !key.is_file() {
+      return Err(AssetError::NotFound(path.to_path_buf()));
+    }
+
+    if let Some(&handle) = self.handles.get(&key) {
+      return Ok(handle);
+    }
+
+    let handle = TextureHandle(self.paths.len());
+    self.paths.push(path.to_path_buf());
+    self.handles.insert(key, handle);
+    Ok(handle)
+  }
+
+  // すでに登録されているファイルのハンドル
+  pub fn handle<P: AsRef<Path>>(&self, path: P) -> Option<TextureHandle> {
+    let key = fs::canonicalize(path).ok()?;
+    self.handles.get(&key).copied()
+  }
+
+  pub fn paths(&self) -> impl Iterator<Item = &Path> {
+    self.paths.iter().map(PathBuf::as_path)
+  }
+
+  pub fn path(&self, handle: TextureHandle) -> Option<&Path> {
+    self.paths.get(handle.0).map(PathBuf::as_path)
+  }
+
+  pub fn len(&self) -> usize {
+    self.paths.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.paths.is_empty()
+  }
+}
+
+// AssetManagerに登録されたテクスチャを、SDLに読み込んで持っておく
+pub struct Textures<'l, T> {
+  creator: &'l TextureCreator<T>,
+  textures: Vec<Texture<'l>>,
+  // AssetManagerを通さずに作ったテクスチャ(TTFで描いた文字など)と、このフレームで使ったかどうか
+  generated: HashMap<String, (Texture<'l>, bool)>,
+}
+
+impl<'l, T> Textures<'l, T> {
+  pub fn new(creator: &'l TextureCreator<T>) -> Self {
+    Self {
+      creator,
+      textures: Vec::new(),
+      generated: HashMap::new(),
+    }
+  }
+
+  // AssetManagerに新しく登録されたテクスチャをすべて読み込む
+  pub fn sync(&mut self, assets: &AssetManager) -> Result<(), AssetError> {
+    while self.textures.len() < assets.len() {
+      let handle = TextureHandle(self.textures.len());
+      let texture = self.load(assets, handle)?;
+      self.textures.push(texture);
+    }
+    Ok(())
+  }
+
+  // ファイルから読み込み直して差し替える。ハンドルはそのまま使える
+  pub fn reload(&mut self, assets: &AssetManager, handle: TextureHandle) -> Result<(), AssetError> {
+    let texture = self.load(assets, handle)?;
+    if let Some(slot) = self.textures.get_mut(handle.0) {
+      *slot = texture;
+    }
+    Ok(())
+  }
+
+  fn load(&self, assets: &AssetManager, handle: TextureHandle) -> Result<Texture<'l>, AssetError> {
+    let path = assets
+      .path(handle)
+      .expect("handle from another AssetManager");
+    self
+      .creator
+      .load_texture(path)
+      .map_err(|message| AssetError::Load {
+        path: path.to_path_buf(),
+        message,
+      })
+  }
+
+  pub fn get(&self, handle: TextureHandle) -> Option<&Texture<'l>> {
+    self.textures.get(handle.0)
+  }
+
+  pub fn get_mut(&mut self, handle: TextureHandle) -> Option<&mut Texture<'l>> {
+    self.textures.get_mut(handle.0)
+  }
+
+  // AssetManagerを通さないテクスチャ(TTFで描いた文字など)を作るときに使う
+  pub fn creator(&self) -> &'l TextureCreator<T> {
+    self.creator
+  }
+
+  // keyで作ったテクスチャ。まだなければcreateで作って取っておき、次からは作り直さずに使い回す
+  // keyには、中身が変わったら別の値になるもの(描く文字列や色など)を入れる
+  pub fn generated<F>(&mut self, key: &str, create: F) -> Result<&Texture<'l>, String>
+  where
+    F: FnOnce(&'l TextureCreator<T>) -> Result<Texture<'l>, String>,
+  {
+    if !self.generated.contains_key(key) {
+      let texture = create(self.creator)?;
+      self.generated.insert(key.to_string(), (texture, true));
+    }
+    let (texture, used) = self.generated.get_mut(key).unwrap();
+    *used = true;
+    Ok(texture)
+  }
+
+  // 1フレーム描き終えたら呼ぶ。このフレームで使わなかったgeneratedのテクスチャを捨てる
+  pub fn end_frame(&mut self) {
+    self
+      .generated
+      .retain(|_, (_, used)| std::mem::replace(used, false));
+  }
+}
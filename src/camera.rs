@@ -0,0 +1,164 @@
+use sdl2::rect::{Point, Rect};
+use specs::prelude::*;
+
+use crate::components::*;
+use crate::tilemap::TileMap;
+use crate::time::DeltaTime;
+use crate::vector::Vec2;
+
+// 画面に映すワールドの範囲を決めるリソース
+#[derive(Debug, Clone)]
+pub struct Camera {
+  // 追いかけるエンティティ
+  pub target: Option<Entity>,
+  // 画面の大きさ(ピクセル)
+  pub viewport: (u32, u32),
+  // 画面の中心付近で、ターゲットが動いてもカメラを動かさない範囲の大きさ(ワールド座標)
+  pub dead_zone: (u32, u32),
+  // 大きいほど早くターゲットに追いつく(1秒あたり)
+  pub follow_speed: f64,
+  // 2.0なら2倍に拡大して映す
+  pub zoom: f64,
+  center: (f64, f64),
+  previous_center: (f64, f64),
+}
+
+impl Default for Camera {
+  fn default() -> Self {
+    Self {
+      target: None,
+      viewport: (640, 480),
+      dead_zone: (64, 48),
+      follow_speed: 8.0,
+      zoom: 1.0,
+      center: (0.0, 0.0),
+      previous_center: (0.0, 0.0),
+    }
+  }
+}
+
+impl Camera {
+  // 補間せずにすぐその場所を映す
+  pub fn look_at(&mut self, point: Point) {
+    self.center = (f64::from(point.x()), f64::from(point.y()));
+    self.previous_center = self.center;
+  }
+
+  // ズームを考慮した、画面に映るワールドの大きさ
+  fn view_size(&self) -> (f64, f64) {
+    (
+      f64::from(self.viewport.0) / self.zoom,
+      f64::from(self.viewport.1) / self.zoom,
+    )
+  }
+
+  // alphaは前のステップから今のステップまでの補間係数(0.0..1.0)
+  fn interpolated_center(&self, alpha: f64) -> (f64, f64) {
+    (
+      self.previous_center.0 + (self.center.0 - self.previous_center.0) * alpha,
+      self.previous_center.1 + (self.center.1 - self.previous_center.1) * alpha,
+    )
+  }
+
+  // 前のステップの位置を今の位置に揃えて、alphaによらず同じ場所を映すようにする
+  pub fn settle(&mut self) {
+    self.previous_center = self.center;
+  }
+
+  // 画面に映っているワールド座標の範囲
+  pub fn view(&self, alpha: f64) -> Rect {
+    let (x, y) = self.interpolated_center(alpha);
+    let (width, height) = self.view_size();
+    Rect::new(
+      (x - width / 2.0).round() as i32,
+      (y - height / 2.0).round() as i32,
+      width.ceil() as u32,
+      height.ceil() as u32,
+    )
+  }
+
+  pub fn world_to_screen(&self, point: Point, alpha: f64) -> Point {
+    let (x, y) = self.interpolated_center(alpha);
+    let (width, height) = self.viewport;
+    Point::new(
+      ((f64::from(point.x()) - x) * self.zoom).round() as i32 + width as i32 / 2,
+      ((f64::from(point.y()) - y) * self.zoom).round() as i32 + height as i32 / 2,
+    )
+  }
+
+  pub fn world_rect_to_screen(&self, rect: Rect, alpha: f64) -> Rect {
+    let top_left = self.world_to_screen(rect.top_left(), alpha);
+    let bottom_right = self.world_to_screen(rect.bottom_right(), alpha);
+    Rect::new(
+      top_left.x(),
+      top_left.y(),
+      (bottom_right.x() - top_left.x()).max(1) as u32,
+      (bottom_right.y() - top_left.y()).max(1) as u32,
+    )
+  }
+
+  // ターゲットがデッドゾーンの外に出た分だけ目標地点をずらし、そこへ滑らかに近づける
+  fn follow(&mut self, target: Vec2, dt: f64) {
+    let follow_axis = |center: f64, target: f64, dead_zone: u32| {
+      let half = f64::from(dead_zone) / 2.0;
+      if target > center + half {
+        target - half
+      } else if target < center - half {
+        target + half
+      } else {
+        center
+      }
+    };
+    let goal = (
+      follow_axis(self.center.0, target.x, self.dead_zone.0),
+      follow_axis(self.center.1, target.y, self.dead_zone.1),
+    );
+
+    let t = 1.0 - (-self.follow_speed * dt).exp();
+    self.center.0 += (goal.0 - self.center.0) * t;
+    self.center.1 += (goal.1 - self.center.1) * t;
+  }
+
+  // マップの外が映らないようにする。マップが画面より小さい軸は中央に寄せる
+  fn clamp_to(&mut self, map: &TileMap) {
+    let (map_width, map_height) = map.pixel_size();
+    if map_width == 0 || map_height == 0 {
+      return;
+    }
+
+    let (view_width, view_height) = self.view_size();
+    let clamp_axis = |center: f64, view: f64, map: u32| {
+      let map = f64::from(map);
+      if view >= map {
+        map / 2.0
+      } else {
+        center.max(view / 2.0).min(map - view / 2.0)
+      }
+    };
+    self.center.0 = clamp_axis(self.center.0, view_width, map_width);
+    self.center.1 = clamp_axis(self.center.1, view_height, map_height);
+  }
+}
+
+// カメラをターゲットに追従させるシステム
+pub struct CameraFollow;
+
+impl<'a> System<'a> for CameraFollow {
+  type SystemData = (
+    Read<'a, DeltaTime>,
+    ReadExpect<'a, TileMap>,
+    Write<'a, Camera>,
+    ReadStorage<'a, Position>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let positions = &data.3;
+    let camera = &mut *data.2;
+    camera.previous_center = camera.center;
+
+    if let Some(target) = camera.target.and_then(|target| positions.get(target)) {
+      camera.follow(target.0, (data.0).0);
+    }
+    camera.clamp_to(&data.1);
+  }
+}
@@ -0,0 +1,295 @@
+use sdl2::rect::Point;
+use std::collections::VecDeque;
+
+use crate::tilemap::{TileId, TileMap, Tileset};
+
+// 同じシードからは必ず同じ列を返す乱数(SplitMix64)
+// randクレートの乱数はバージョンによって列が変わりうるので、再現性のために自前で持つ
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Rng(seed)
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  // 0.0以上1.0未満
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+// 洞窟に使うタイル。タイルセットの並びに合わせて呼ぶ側が決める
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaveTiles {
+  pub floor: TileId,
+  pub wall: TileId,
+  pub exit: TileId,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaveSettings {
+  pub seed: u64,
+  pub width: i32,
+  pub height: i32,
+  // 最初に壁で埋めるマスの割合(0.0..1.0)
+  pub fill_ratio: f64,
+  // セル・オートマトンで均す回数。多いほど洞窟の壁がなめらかになる
+  pub smoothing_passes: u32,
+  // 敵を置く数。床が足りなければ置ける分だけ
+  pub enemy_count: usize,
+  // 敵はスポーン地点からこの歩数より遠い床に置く
+  pub enemy_min_distance: u32,
+  pub tiles: CaveTiles,
+}
+
+impl CaveSettings {
+  // tiles以外はほどよい大きさの洞窟になる値にする
+  pub fn new(tiles: CaveTiles) -> Self {
+    Self {
+      seed: 0,
+      width: 80,
+      height: 60,
+      fill_ratio: 0.45,
+      smoothing_passes: 5,
+      enemy_count: 6,
+      enemy_min_distance: 12,
+      tiles,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cave {
+  pub map: TileMap,
+  // プレイヤーを置くタイルと出口のタイル
+  pub spawn: (i32, i32),
+  pub exit: (i32, i32),
+  // 敵を置くタイル
+  pub enemies: Vec<(i32, i32)>,
+}
+
+impl Cave {
+  // スポーン地点のタイルの中心のワールド座標
+  pub fn spawn_point(&self) -> Point {
+    self.map.tile_rect(self.spawn.0, self.spawn.1).center()
+  }
+
+  // 敵を置くタイルの中心のワールド座標
+  pub fn enemy_points(&self) -> Vec<Point> {
+    self
+      .enemies
+      .iter()
+      .map(|&(x, y)| self.map.tile_rect(x, y).center())
+      .collect()
+  }
+}
+
+// セル・オートマトンで洞窟を作る
+// 1. ランダムに壁を置く 2. 周りの壁の数で均す 3. 一番大きくつながった空間だけを残す
+// 4. 中央に一番近い床をスポーン地点に、そこから一番遠い床を出口にする
+// 5. スポーン地点から離れた床に敵を散らばらせる
+pub fn generate(settings: &CaveSettings, tileset: Tileset) -> Cave {
+  let (width, height) = (settings.width.max(3), settings.height.max(3));
+  let mut rng = Rng::new(settings.seed);
+
+  let mut walls = Grid::new(width, height, true);
+  for y in 1..height - 1 {
+    for x in 1..width - 1 {
+      walls.set(x, y, rng.next_f64() < settings.fill_ratio);
+    }
+  }
+
+  for _ in 0..settings.smoothing_passes {
+    walls = smooth(&walls);
+  }
+
+  keep_largest_region(&mut walls);
+
+  let spawn = nearest_floor(&walls, (width / 2, height / 2)).unwrap_or_else(|| {
+    // 全部壁になってしまったら、中央に1マスだけ床を掘る
+    walls.set(width / 2, height / 2, false);
+    (width / 2, height / 2)
+  });
+  let exit = farthest_floor(&walls, spawn);
+  let enemies = enemy_tiles(&walls, spawn, exit, settings, &mut rng);
+
+  let mut map = TileMap::new(width, height, tileset, Some(settings.tiles.wall));
+  for y in 0..height {
+    for x in 0..width {
+      if !walls.get(x, y) {
+        map.set(x, y, Some(settings.tiles.floor));
+      }
+    }
+  }
+  map.set(exit.0, exit.1, Some(settings.tiles.exit));
+
+  Cave {
+    map,
+    spawn,
+    exit,
+    enemies,
+  }
+}
+
+// trueが壁の2次元配列
+#[derive(Debug, Clone)]
+struct Grid {
+  width: i32,
+  height: i32,
+  cells: Vec<bool>,
+}
+
+impl Grid {
+  fn new(width: i32, height: i32, value: bool) -> Self {
+    Self {
+      width,
+      height,
+      cells: vec![value; (width * height) as usize],
+    }
+  }
+
+  // 範囲の外は壁とみなす
+  fn get(&self, x: i32, y: i32) -> bool {
+    if x < 0 || y < 0 || x >= self.width || y >= self.height {
+      return true;
+    }
+    self.cells[(y * self.width + x) as usize]
+  }
+
+  fn set(&mut self, x: i32, y: i32, value: bool) {
+    self.cells[(y * self.width + x) as usize] = value;
+  }
+
+  fn floors(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+    (0..self.height)
+      .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+      .filter(move |&(x, y)| !self.get(x, y))
+  }
+}
+
+// 周りの8マスのうち5マス以上が壁なら壁に、3マス以下なら床にする。外周は必ず壁のまま
+fn smooth(walls: &Grid) -> Grid {
+  let mut next = walls.clone();
+  for y in 1..walls.height - 1 {
+    for x in 1..walls.width - 1 {
+      let neighbours = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| (dx, dy) != (0, 0) && walls.get(x + dx, y + dy))
+        .count();
+      if neighbours > 4 {
+        next.set(x, y, true);
+      } else if neighbours < 4 {
+        next.set(x, y, false);
+      }
+    }
+  }
+  next
+}
+
+const NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+// startから上下左右に歩いて行ける床までの歩数。行けないマスはNone
+fn distances(walls: &Grid, start: (i32, i32)) -> Vec<Option<u32>> {
+  let mut distances = vec![None; walls.cells.len()];
+  let index = |(x, y): (i32, i32)| (y * walls.width + x) as usize;
+
+  distances[index(start)] = Some(0);
+  let mut queue = VecDeque::new();
+  queue.push_back(start);
+  while let Some((x, y)) = queue.pop_front() {
+    let distance = distances[index((x, y))].unwrap_or(0);
+    for (dx, dy) in NEIGHBOURS.iter() {
+      let next = (x + dx, y + dy);
+      if !walls.get(next.0, next.1) && distances[index(next)].is_none() {
+        distances[index(next)] = Some(distance + 1);
+        queue.push_back(next);
+      }
+    }
+  }
+  distances
+}
+
+// 床がつながった領域のうち一番大きいものだけを残し、ほかは壁で埋める
+fn keep_largest_region(walls: &mut Grid) {
+  let mut region_of = vec![None; walls.cells.len()];
+  let mut sizes = Vec::new();
+
+  let floors: Vec<(i32, i32)> = walls.floors().collect();
+  for &(x, y) in &floors {
+    let index = (y * walls.width + x) as usize;
+    if region_of[index].is_some() {
+      continue;
+    }
+
+    let region = sizes.len();
+    let reachable = distances(walls, (x, y));
+    let mut size = 0;
+    for (i, distance) in reachable.iter().enumerate() {
+      if distance.is_some() {
+        region_of[i] = Some(region);
+        size += 1;
+      }
+    }
+    sizes.push(size);
+  }
+
+  // 同じ大きさなら先に見つかった領域を残す
+  let largest = match (0..sizes.len()).max_by_key(|&region| (sizes[region], usize::MAX - region)) {
+    Some(largest) => largest,
+    None => return,
+  };
+  for (x, y) in floors {
+    if region_of[(y * walls.width + x) as usize] != Some(largest) {
+      walls.set(x, y, true);
+    }
+  }
+}
+
+fn nearest_floor(walls: &Grid, target: (i32, i32)) -> Option<(i32, i32)> {
+  walls.floors().min_by_key(|&(x, y)| {
+    let (dx, dy) = (x - target.0, y - target.1);
+    dx * dx + dy * dy
+  })
+}
+
+fn farthest_floor(walls: &Grid, start: (i32, i32)) -> (i32, i32) {
+  let reachable = distances(walls, start);
+  walls
+    .floors()
+    .max_by_key(|&(x, y)| reachable[(y * walls.width + x) as usize])
+    .unwrap_or(start)
+}
+
+// スポーン地点から十分に遠い床から、重ならないようにenemy_count個を選ぶ
+fn enemy_tiles(
+  walls: &Grid,
+  spawn: (i32, i32),
+  exit: (i32, i32),
+  settings: &CaveSettings,
+  rng: &mut Rng,
+) -> Vec<(i32, i32)> {
+  let reachable = distances(walls, spawn);
+  let mut candidates: Vec<(i32, i32)> = walls
+    .floors()
+    .filter(|&tile| tile != exit)
+    .filter(|&(x, y)| {
+      reachable[(y * walls.width + x) as usize].is_some_and(|d| d >= settings.enemy_min_distance)
+    })
+    .collect();
+
+  let mut enemies = Vec::new();
+  while enemies.len() < settings.enemy_count && !candidates.is_empty() {
+    let i = (rng.next_u64() % candidates.len() as u64) as usize;
+    enemies.push(candidates.swap_remove(i));
+  }
+  enemies
+}
@@ -0,0 +1,92 @@
+use sdl2::rect::Rect;
+use specs::prelude::*;
+
+use crate::tilemap::TileMap;
+
+// 何とぶつかったか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collided {
+  // 通り抜けられないタイルの座標
+  Tile(i32, i32),
+  Entity(Entity),
+}
+
+// entityが移動中にotherとぶつかった、または重なったことを知らせるイベント
+// ダメージやアイテムの取得などはこのイベントを読んで処理する
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+  pub entity: Entity,
+  pub other: Collided,
+}
+
+// ワールド座標に置かれた当たり判定
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+  pub entity: Entity,
+  pub rect: Rect,
+  pub solid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+  Horizontal,
+  Vertical,
+}
+
+// hitboxをaxis方向にdistanceだけ動かしたとき、壁や他のエンティティにめり込まない所まで移動量を縮める
+// 戻り値は実際に動ける量と、移動を止めた相手
+pub fn sweep(
+  entity: Entity,
+  hitbox: Rect,
+  axis: Axis,
+  distance: i32,
+  map: &TileMap,
+  bodies: &[Body],
+) -> (i32, Vec<Collided>) {
+  if distance == 0 {
+    return (0, Vec::new());
+  }
+
+  // 移動の途中で通り抜けてしまわないように、移動前から移動後までの範囲をまとめて調べる
+  let mut moved = hitbox;
+  match axis {
+    Axis::Horizontal => moved.offset(distance, 0),
+    Axis::Vertical => moved.offset(0, distance),
+  }
+  let swept = hitbox.union(moved);
+
+  let tiles = map
+    .tiles_in(swept)
+    .filter(|&(x, y)| map.is_solid(x, y))
+    .map(|(x, y)| (Collided::Tile(x, y), map.tile_rect(x, y)));
+  let others = bodies
+    .iter()
+    .filter(|body| body.solid && body.entity != entity)
+    .map(|body| (Collided::Entity(body.entity), body.rect));
+
+  let mut allowed = distance;
+  let mut blockers = Vec::new();
+  for (other, rect) in tiles.chain(others) {
+    // 最初から重なっている相手に引っかかって動けなくならないように無視する
+    if !swept.has_intersection(rect) || hitbox.has_intersection(rect) {
+      continue;
+    }
+
+    let gap = match (axis, distance > 0) {
+      (Axis::Horizontal, true) => rect.left() - hitbox.right(),
+      (Axis::Horizontal, false) => rect.right() - hitbox.left(),
+      (Axis::Vertical, true) => rect.top() - hitbox.bottom(),
+      (Axis::Vertical, false) => rect.bottom() - hitbox.top(),
+    };
+
+    if gap.abs() < allowed.abs() {
+      allowed = gap;
+      blockers.clear();
+    }
+    if gap == allowed {
+      blockers.push(other);
+    }
+  }
+
+  (allowed, blockers)
+}
@@ -0,0 +1,276 @@
+use sdl2::rect::Rect;
+use shrev::{EventChannel, ReaderId};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+use crate::components::*;
+use crate::input::{Action, InputState};
+use crate::spatial::SpatialHash;
+use crate::time::DeltaTime;
+use crate::vector::Vec2;
+
+// 点滅の間隔(秒)。この時間ごとに見えたり薄くなったりする
+const FLASH_INTERVAL: f64 = 0.08;
+// 点滅して薄くなっているときのアルファ値
+const FLASH_ALPHA: u8 = 64;
+// 弾き飛ばされてから、また自分で歩けるようになるまでの秒数
+const KNOCKBACK_TIME: f64 = 0.15;
+
+// 攻撃が当たった、倒れたことを知らせるイベント
+// スプライトの点滅や効果音などは、このイベントを読んで鳴らす
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CombatEvent {
+  // entityがsourceからamountのダメージを受けた
+  Damaged {
+    entity: Entity,
+    source: Entity,
+    amount: u32,
+  },
+  // entityの体力が0になった。プレイヤー以外はこのステップの終わりに消える
+  Died {
+    entity: Entity,
+  },
+}
+
+// プレイヤーが倒れたかどうか。Playingのシーンがこれを見てゲームオーバーにする
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayerDefeated(pub bool);
+
+// 敵味方の違うHitboxとHurtboxが重なったらダメージを与えるシステム
+// ダメージを受けると無敵時間の間は次のダメージを受けず、攻撃した相手から離れる向きに弾き飛ばされる
+pub struct Combat;
+
+impl<'a> System<'a> for Combat {
+  type SystemData = (
+    Entities<'a>,
+    Read<'a, DeltaTime>,
+    Write<'a, EventChannel<CombatEvent>>,
+    Write<'a, PlayerDefeated>,
+    ReadStorage<'a, Position>,
+    WriteStorage<'a, Health>,
+    ReadStorage<'a, Hurtbox>,
+    ReadStorage<'a, Hitbox>,
+    ReadStorage<'a, Damage>,
+    WriteStorage<'a, Velocity>,
+    ReadStorage<'a, InputControlled>,
+    WriteStorage<'a, Movement>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let dt = (data.1).0;
+
+    for health in (&mut data.5).join() {
+      health.invulnerable = (health.invulnerable - dt).max(0.0);
+    }
+
+    // 攻撃の当たる範囲をSpatialHashに入れて、Hurtboxの近くにあるものだけを調べる
+    // SpatialHashのリソースは当たり判定(Collider)用なので、ここで別に作る
+    // 倒れて消されたエンティティの攻撃もこのステップの間は当たるように、陣営とダメージも写しておく
+    let mut hitboxes = SpatialHash::default();
+    let mut attacks = HashMap::new();
+    for (entity, pos, hitbox, damage) in (&data.0, &data.4, &data.7, &data.8).join() {
+      hitboxes.insert(entity, hitbox.world_rect(pos.0));
+      attacks.insert(entity, (hitbox.team, *damage));
+    }
+
+    let mut events = Vec::new();
+    for (entity, pos, health, hurtbox, vel, player, movement) in (
+      &data.0,
+      &data.4,
+      &mut data.5,
+      &data.6,
+      (&mut data.9).maybe(),
+      data.10.maybe(),
+      (&mut data.11).maybe(),
+    )
+      .join()
+    {
+      if health.is_dead() || health.invulnerable > 0.0 {
+        continue;
+      }
+      let rect = hurtbox.world_rect(pos.0);
+      // 同時にいくつ重なっていても、1回に受けるのは最初の1つだけ
+      let hit = hitboxes
+        .query_rect(rect)
+        .into_iter()
+        .find(|&source| source != entity && attacks[&source].0 != hurtbox.team);
+      let source = match hit {
+        Some(source) => source,
+        None => continue,
+      };
+      let hitbox = hitboxes.rect(source).unwrap();
+      let damage = attacks[&source].1;
+
+      health.current = health.current.saturating_sub(damage.amount);
+      health.invulnerable = health.invulnerability;
+      if let Some(vel) = vel {
+        let away = (Vec2::from(rect.center()) - Vec2::from(hitbox.center())).normalized();
+        vel.0 = away * damage.knockback;
+        // 歩こうとする向きに速度を上書きされないように、しばらく自分では歩かせない
+        if let Some(movement) = movement {
+          movement.stunned = KNOCKBACK_TIME;
+        }
+      }
+      events.push(CombatEvent::Damaged {
+        entity,
+        source,
+        amount: damage.amount,
+      });
+
+      if health.is_dead() {
+        events.push(CombatEvent::Died { entity });
+        // プレイヤーは消さずに残し、ゲームオーバーの画面の後ろに倒れたまま描く
+        if player.is_some() {
+          (data.3).0 = true;
+        } else {
+          data.0.delete(entity).unwrap();
+        }
+      }
+    }
+
+    data.2.iter_write(events);
+  }
+}
+
+// Attackの行動が押されたら、操作しているエンティティの向いている方向に攻撃の当たる範囲を出すシステム
+// 当たる範囲は別のエンティティにして、Lifetimeが切れたらExpireが消す
+pub struct PlayerAttack;
+
+impl<'a> System<'a> for PlayerAttack {
+  type SystemData = (
+    Entities<'a>,
+    Read<'a, InputState>,
+    ReadStorage<'a, InputControlled>,
+    ReadStorage<'a, MeleeAttack>,
+    ReadStorage<'a, MovementAnimation>,
+    ReadStorage<'a, Hurtbox>,
+    WriteStorage<'a, Position>,
+    WriteStorage<'a, Hitbox>,
+    WriteStorage<'a, Damage>,
+    WriteStorage<'a, Lifetime>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    if !data.1.just_pressed(Action::Attack) {
+      return;
+    }
+
+    let attacks: Vec<_> = (&data.2, &data.3, data.4.maybe(), data.5.maybe(), &data.6)
+      .join()
+      .map(|(_, attack, animation, hurtbox, pos)| {
+        // アニメーションがなければ下を向いていることにする
+        let facing = animation.map_or(Direction::Down, |animation| animation.facing);
+        // 味方の攻撃は、攻撃した本人と同じ陣営にする
+        let team = hurtbox.map_or(Team::Player, |hurtbox| hurtbox.team);
+        (*attack, pos.0 + facing.unit_vector() * attack.reach, team)
+      })
+      .collect();
+
+    for (attack, center, team) in attacks {
+      let (width, height) = attack.size;
+      let hitbox = Hitbox {
+        rect: Rect::new(-(width as i32) / 2, -(height as i32) / 2, width, height),
+        team,
+      };
+      let lifetime = Lifetime {
+        remaining: attack.duration,
+      };
+      let slash = data.0.create();
+      data.6.insert(slash, Position(center)).unwrap();
+      data.7.insert(slash, hitbox).unwrap();
+      data.8.insert(slash, attack.damage).unwrap();
+      data.9.insert(slash, lifetime).unwrap();
+    }
+  }
+}
+
+// Lifetimeが切れたエンティティを消すシステム
+pub struct Expire;
+
+impl<'a> System<'a> for Expire {
+  type SystemData = (
+    Entities<'a>,
+    Read<'a, DeltaTime>,
+    WriteStorage<'a, Lifetime>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let dt = (data.1).0;
+    for (entity, lifetime) in (&data.0, &mut data.2).join() {
+      lifetime.remaining -= dt;
+      if lifetime.remaining <= 0.0 {
+        data.0.delete(entity).unwrap();
+      }
+    }
+  }
+}
+
+// ダメージを受けたエンティティを、無敵時間の間点滅させるシステム
+// CombatEventを読む例にもなっている
+#[derive(Default)]
+pub struct HitFlash {
+  reader: Option<ReaderId<CombatEvent>>,
+}
+
+impl<'a> System<'a> for HitFlash {
+  type SystemData = (
+    Entities<'a>,
+    Read<'a, DeltaTime>,
+    Read<'a, EventChannel<CombatEvent>>,
+    ReadStorage<'a, Health>,
+    WriteStorage<'a, Flash>,
+    WriteStorage<'a, SpriteStyle>,
+  );
+
+  fn setup(&mut self, res: &mut Resources) {
+    Self::SystemData::setup(res);
+    self.reader = Some(
+      res
+        .fetch_mut::<EventChannel<CombatEvent>>()
+        .register_reader(),
+    );
+  }
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let dt = (data.1).0;
+
+    let reader = self.reader.as_mut().unwrap();
+    for event in data.2.read(reader) {
+      if let CombatEvent::Damaged { entity, .. } = *event {
+        // 倒れたエンティティはこのステップの終わりに消えるので点滅させない
+        let health = match data.3.get(entity) {
+          Some(health) if !health.is_dead() && health.invulnerability > 0.0 => health,
+          _ => continue,
+        };
+        let flash = Flash {
+          remaining: health.invulnerability,
+        };
+        data.4.insert(entity, flash).unwrap();
+      }
+    }
+
+    let mut finished = Vec::new();
+    for (entity, flash) in (&data.0, &mut data.4).join() {
+      flash.remaining -= dt;
+      let faded =
+        flash.remaining > 0.0 && ((flash.remaining / FLASH_INTERVAL) as u32).is_multiple_of(2);
+      let alpha = if faded { FLASH_ALPHA } else { 255 };
+      match data.5.get_mut(entity) {
+        Some(style) => style.alpha = alpha,
+        None => {
+          let style = SpriteStyle {
+            alpha,
+            ..SpriteStyle::default()
+          };
+          data.5.insert(entity, style).unwrap();
+        }
+      }
+      if flash.remaining <= 0.0 {
+        finished.push(entity);
+      }
+    }
+    for entity in finished {
+      data.4.remove(entity);
+    }
+  }
+}
@@ -1,6 +1,15 @@
+use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
 use specs::prelude::*;
 use specs_derive::Component;
+use std::path::PathBuf;
+
+use crate::ai::Behavior;
+use crate::assets::TextureHandle;
+use crate::cavegen::Rng;
+use crate::movement::MovementTuning;
+use crate::vector::Vec2;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -8,40 +17,447 @@ pub enum Direction {
   Down,
   Left,
   Right,
+  UpLeft,
+  UpRight,
+  DownLeft,
+  DownRight,
 }
 
-// 現在位置を表すエンティティ
+impl Direction {
+  // vectorに一番近い方向。diagonalがfalseなら上下左右のどれかにする
+  pub fn from_vector(vector: Vec2, diagonal: bool) -> Option<Direction> {
+    use self::Direction::*;
+    let Vec2 { x, y } = vector;
+    if x == 0.0 && y == 0.0 {
+      return None;
+    }
+
+    if !diagonal {
+      return Some(match (x.abs() >= y.abs(), x > 0.0, y > 0.0) {
+        (true, true, _) => Right,
+        (true, false, _) => Left,
+        (false, _, true) => Down,
+        (false, _, false) => Up,
+      });
+    }
+
+    // yは下向きが正なので、角度は右から時計回りに増える
+    let sector = (y.atan2(x) / std::f64::consts::FRAC_PI_4).round() as i32;
+    Some(match sector.rem_euclid(8) {
+      0 => Right,
+      1 => DownRight,
+      2 => Down,
+      3 => DownLeft,
+      4 => Left,
+      5 => UpLeft,
+      6 => Up,
+      _ => UpRight,
+    })
+  }
+
+  // その方向を向いた長さ1のベクトル
+  pub fn unit_vector(self) -> Vec2 {
+    use self::Direction::*;
+    let diagonal = std::f64::consts::FRAC_1_SQRT_2;
+    match self {
+      Up => Vec2::new(0.0, -1.0),
+      Down => Vec2::new(0.0, 1.0),
+      Left => Vec2::new(-1.0, 0.0),
+      Right => Vec2::new(1.0, 0.0),
+      UpLeft => Vec2::new(-diagonal, -diagonal),
+      UpRight => Vec2::new(diagonal, -diagonal),
+      DownLeft => Vec2::new(-diagonal, diagonal),
+      DownRight => Vec2::new(diagonal, diagonal),
+    }
+  }
+}
+
+// 現在位置を表すエンティティ。ピクセルより細かく動けるように小数で持ち、描画するときに丸める
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
-pub struct Position(pub Point);
+pub struct Position(pub Vec2);
 
-// 方向と速さを表すエンティティ
+// 1つ前のシミュレーションステップでの位置。描画時の補間に使う
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
-pub struct Velocity {
-  pub speed: i32,
-  pub direction: Direction,
+pub struct PreviousPosition(pub Vec2);
+
+// 1秒あたりに進むピクセル数を表すエンティティ
+#[derive(Component, Debug, Default)]
+#[storage(VecStorage)]
+pub struct Velocity(pub Vec2);
+
+impl Velocity {
+  // 進んでいる向き。止まっていればNone
+  pub fn direction(&self, diagonal: bool) -> Option<Direction> {
+    Direction::from_vector(self.0, diagonal)
+  }
+}
+
+// 自分で歩くエンティティ。入力やAIはdesiredだけを決め、PhysicsがVelocityをそこへ近づける
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Movement {
+  pub tuning: MovementTuning,
+  // 進みたい向き。長さ(0.0..1.0)はmax_speedに対する割合で、長さ0なら止まろうとする
+  pub desired: Vec2,
+  // 弾き飛ばされている残りの秒数。この間はdesiredを無視して、弾き飛ばされた速度のまま動く
+  pub stunned: f64,
+}
+
+impl Movement {
+  pub fn new(tuning: MovementTuning) -> Self {
+    Self {
+      tuning,
+      desired: Vec2::ZERO,
+      stunned: 0.0,
+    }
+  }
 }
 
 #[derive(Component, Debug, Clone)]
 #[storage(VecStorage)]
 pub struct Sprite {
-  // レンダリングするスプライトシートを表す番号
-  pub spritesheet: usize,
+  // レンダリングするスプライトシート
+  pub spritesheet: TextureHandle,
   // レンダリングするスプライトの領域
   pub region: Rect,
+  // 左右・上下を反転して描くかどうか。アニメーションのフレームごとに決まる
+  pub flip: Flip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub struct Flip {
+  pub horizontal: bool,
+  pub vertical: bool,
+}
+
+// スプライトの回転と色の変え方を表すエンティティ。持っていなければそのまま描く
+// Spriteはアニメーションで書き換えられるので、ダメージの点滅やフェードアウトはこちらで表す
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct SpriteStyle {
+  // 時計回りの回転角度(度)
+  pub angle: f64,
+  // 回転の中心。スプライトの左上を基準にした座標で、Noneならスプライトの中心
+  pub pivot: Option<Point>,
+  // スプライトの色に掛ける色。白ならそのままの色になる
+  pub tint: Color,
+  // 不透明度(0で透明、255で不透明)
+  pub alpha: u8,
+}
+
+impl Default for SpriteStyle {
+  fn default() -> Self {
+    Self {
+      angle: 0.0,
+      pivot: None,
+      tint: Color::RGB(255, 255, 255),
+      alpha: 255,
+    }
+  }
+}
+
+// 最後のフレームまで進んだあとの動き
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum LoopMode {
+  // 最初のフレームに戻る
+  Loop,
+  // 最後のフレームで止まる
+  Once,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+  pub sprite: Sprite,
+  // このフレームを表示しておく時間(秒)
+  pub duration: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+  pub frames: Vec<AnimationFrame>,
+  pub mode: LoopMode,
+}
+
+impl AnimationClip {
+  // current_frameの次に表示するフレームの番号
+  pub fn next_frame(&self, current_frame: usize) -> usize {
+    match self.mode {
+      LoopMode::Loop => (current_frame + 1) % self.frames.len(),
+      LoopMode::Once => (current_frame + 1).min(self.frames.len() - 1),
+    }
+  }
+}
+
+// 上下左右の向きごとのクリップ
+#[derive(Debug, Clone)]
+pub struct DirectionalClips {
+  pub up: AnimationClip,
+  pub down: AnimationClip,
+  pub left: AnimationClip,
+  pub right: AnimationClip,
+}
+
+impl DirectionalClips {
+  pub fn get(&self, direction: Direction) -> &AnimationClip {
+    use self::Direction::*;
+    // 斜めのクリップはないので、横向きのクリップで代用する
+    match direction {
+      Up => &self.up,
+      Down => &self.down,
+      Left | UpLeft | DownLeft => &self.left,
+      Right | UpRight | DownRight => &self.right,
+    }
+  }
 }
 
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct MovementAnimation {
   pub current_frame: usize,
-  pub up_frames: Vec<Sprite>,
-  pub down_frames: Vec<Sprite>,
-  pub left_frames: Vec<Sprite>,
-  pub right_frames: Vec<Sprite>,
+  // 今のフレームを表示し始めてから経過した時間(秒)
+  pub elapsed: f64,
+  // 今再生しているクリップの向きと、歩いているかどうか
+  pub facing: Direction,
+  pub moving: bool,
+  pub walk: DirectionalClips,
+  // 止まっているときのクリップ。Noneなら歩行クリップの最初のフレームで止める
+  pub idle: Option<DirectionalClips>,
 }
 
+// MovementAnimationを読み込んだ定義ファイル。ファイルが変更されたら読み込み直す
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct AnimationSource(pub PathBuf);
+
+// Movementの設定を読み込んだファイル。ファイルが変更されたら読み込み直す
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct MovementSource(pub PathBuf);
+
+// キーボードやゲームパッドの入力(InputState)で操作するエンティティ
 #[derive(Component, Debug, Default)]
 #[storage(NullStorage)]
-pub struct KeyboardControlled;
+pub struct InputControlled;
+
+// Behaviorの設定を読み込んだファイル。ファイルが変更されたら読み込み直す
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct BehaviorSource(pub PathBuf);
+
+// AIで動くエンティティが今していること
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AiState {
+  // その場で立ち止まっている。timer秒たったらうろつく
+  Idle { timer: f64 },
+  // goalへ歩いていく。timer秒たっても着かなければあきらめる
+  Wander { goal: Vec2, timer: f64 },
+  // プレイヤーを最後に見た場所を目指す。見失ってからmemory秒たったらあきらめる
+  Chase { last_seen: Vec2, memory: f64 },
+  // threatから遠ざかる
+  Flee { threat: Vec2 },
+  // directionへまっすぐ飛びかかる。timer秒たったら終わる
+  Attack { direction: Vec2, timer: f64 },
+}
+
+// AI(EnemyAi)で動くエンティティ。Movementも持たせておく
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Ai {
+  pub behavior: Behavior,
+  pub state: AiState,
+  // うろつく範囲の中心
+  pub home: Vec2,
+  // これから通る点。最後の要素が次に目指す点
+  pub path: Vec<Vec2>,
+  // 経路を探し直すまでの秒数
+  pub repath_timer: f64,
+  // 次に飛びかかれるようになるまでの秒数
+  pub cooldown: f64,
+  // うろつく先や立ち止まる時間を決める。エンティティごとに持って、同じシードなら同じように動く
+  pub rng: Rng,
+}
+
+impl Ai {
+  pub fn new(behavior: Behavior, home: Vec2, seed: u64) -> Self {
+    Self {
+      behavior,
+      state: AiState::Idle {
+        timer: behavior.idle_time,
+      },
+      home,
+      path: Vec::new(),
+      repath_timer: 0.0,
+      cooldown: 0.0,
+      rng: Rng::new(seed),
+    }
+  }
+}
+
+// 当たり判定を表すエンティティ
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Collider {
+  // Positionを基準にした当たり判定の矩形
+  pub hitbox: Rect,
+  // trueなら壁や他のsolidな当たり判定を通り抜けない。falseならイベントだけを送る
+  pub solid: bool,
+}
+
+impl Collider {
+  // 当たり判定をワールド座標に置き直す。位置は一番近いピクセルに丸める
+  pub fn world_rect(&self, position: Vec2) -> Rect {
+    offset_rect(self.hitbox, position)
+  }
+}
+
+// Positionを基準にした矩形をワールド座標に置き直す
+fn offset_rect(mut rect: Rect, position: Vec2) -> Rect {
+  let position = position.to_point();
+  rect.offset(position.x(), position.y());
+  rect
+}
+
+// 敵味方の区別。同じ陣営のHitboxとHurtboxは重なってもダメージを与えない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Team {
+  Player,
+  Enemy,
+}
+
+// 体力を持つエンティティ。0になると倒れる
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Health {
+  pub current: u32,
+  pub max: u32,
+  // ダメージを受けてから、次のダメージを受けなくなる秒数
+  pub invulnerability: f64,
+  // 無敵が終わるまでの残りの秒数
+  pub invulnerable: f64,
+}
+
+impl Health {
+  pub fn new(max: u32, invulnerability: f64) -> Self {
+    Self {
+      current: max,
+      max,
+      invulnerability,
+      invulnerable: 0.0,
+    }
+  }
+
+  pub fn is_dead(&self) -> bool {
+    self.current == 0
+  }
+}
+
+// 攻撃を受ける範囲。壁とぶつかる範囲(Collider)とは別に決める
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Hurtbox {
+  // Positionを基準にした矩形
+  pub rect: Rect,
+  pub team: Team,
+}
+
+impl Hurtbox {
+  pub fn world_rect(&self, position: Vec2) -> Rect {
+    offset_rect(self.rect, position)
+  }
+}
+
+// 攻撃が当たる範囲。ここに重なった違う陣営のHurtboxにDamageを与える
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Hitbox {
+  // Positionを基準にした矩形
+  pub rect: Rect,
+  pub team: Team,
+}
+
+impl Hitbox {
+  pub fn world_rect(&self, position: Vec2) -> Rect {
+    offset_rect(self.rect, position)
+  }
+}
+
+// Hitboxが当たったときに与えるダメージ
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(VecStorage)]
+pub struct Damage {
+  pub amount: u32,
+  // 当たった相手を弾き飛ばす速さ(1秒あたりのピクセル数)
+  pub knockback: f64,
+}
+
+// Attackの行動で、向いている方向に短い間だけHitboxを出すエンティティ
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(HashMapStorage)]
+pub struct MeleeAttack {
+  pub damage: Damage,
+  // 自分の位置から、攻撃の当たる範囲の中心までの距離
+  pub reach: f64,
+  // 攻撃の当たる範囲の(幅, 高さ)
+  pub size: (u32, u32),
+  // 当たる範囲を出しておく秒数
+  pub duration: f64,
+}
+
+// この秒数が過ぎたら消えるエンティティ。攻撃の当たる範囲などに使う
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Lifetime {
+  pub remaining: f64,
+}
+
+// ダメージを受けて点滅しているエンティティ。HitFlashが付けたり外したりする
+#[derive(Component, Debug, Clone)]
+#[storage(HashMapStorage)]
+pub struct Flash {
+  // 点滅が終わるまでの秒数
+  pub remaining: f64,
+}
+
+// 描画する層。後に書いたものほど手前に描かれる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize)]
+pub enum RenderLayer {
+  // 床の模様など、タイルのすぐ上に描くもの
+  Background,
+  // プレイヤーや敵など、y座標で前後が決まるもの
+  #[default]
+  Entity,
+  // 木の葉や天井など、いつもエンティティより手前に描くもの
+  Foreground,
+  // 画面に固定して描くもの。Positionはスクリーン座標として扱う
+  Ui,
+}
+
+// 描画の順番を決めるエンティティ。持っていなければEntity層に描く
+// 同じ層の中では、スプライトの下端のy座標が小さいものから描く
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[storage(VecStorage)]
+pub struct Depth {
+  pub layer: RenderLayer,
+  // 下端のy座標に足してから並べる。足元が同じ高さのものの前後を決めたいときに使う
+  pub offset: i32,
+}
+
+// 画面に固定して描く文字列。Positionはスクリーン座標で、文字列の左上を表す
+// スプライトをすべて描いたあとに描く
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Text {
+  pub content: String,
+  pub color: Color,
+  // 1行の高さ(ピクセル)の目安。ビットマップフォントでは一番近い整数倍に拡大する
+  pub size: u32,
+}
+
+// targetの体力をTextに書き出すエンティティ。HealthHudが毎ステップ書き直す
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(HashMapStorage)]
+pub struct HealthDisplay {
+  pub target: Entity,
+}
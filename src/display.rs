@@ -0,0 +1,101 @@
+use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// 画面の設定ファイル(RON)の中身。例はassets/display.ronを参照
+// ゲームは常にlogical_sizeの大きさで描き、それをウィンドウに合わせて拡大する
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+  pub title: String,
+  // ゲームを描く解像度
+  pub logical_size: (u32, u32),
+  // 起動したときのウィンドウの大きさ
+  pub window_size: (u32, u32),
+  // trueなら整数倍にだけ拡大して、ドットがにじまないようにする
+  pub integer_scaling: bool,
+  pub fullscreen: bool,
+  pub resizable: bool,
+  // 文字を描くTrueTypeフォント。ttfフィーチャーを有効にしてビルドしたときだけ使い、
+  // Noneか読み込めなかったときはビットマップフォントで描く
+  pub ttf_font: Option<String>,
+}
+
+impl Default for DisplaySettings {
+  fn default() -> Self {
+    Self {
+      title: "SDL".to_string(),
+      logical_size: (640, 480),
+      window_size: (640, 480),
+      integer_scaling: true,
+      fullscreen: false,
+      resizable: true,
+      ttf_font: None,
+    }
+  }
+}
+
+impl DisplaySettings {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let settings: Self = ron::de::from_str(source).map_err(|e| e.to_string())?;
+    if settings.logical_size.0 == 0 || settings.logical_size.1 == 0 {
+      return Err("logical_size must not be zero".to_string());
+    }
+    Ok(settings)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  // 大きさがoutputの画面のどこにゲームを描くか。縦横比を保ち、余った部分は黒い帯になる
+  // ウィンドウがlogical_sizeより小さいときは、整数倍にできないので縮小する
+  pub fn letterbox(&self, output: (u32, u32)) -> Rect {
+    let (logical_width, logical_height) = self.logical_size;
+    let scale = f64::min(
+      f64::from(output.0) / f64::from(logical_width),
+      f64::from(output.1) / f64::from(logical_height),
+    );
+    let scale = if self.integer_scaling && scale >= 1.0 {
+      scale.floor()
+    } else {
+      scale
+    };
+
+    let width = ((f64::from(logical_width) * scale).round() as u32).max(1);
+    let height = ((f64::from(logical_height) * scale).round() as u32).max(1);
+    Rect::new(
+      (output.0 as i32 - width as i32) / 2,
+      (output.1 as i32 - height as i32) / 2,
+      width,
+      height,
+    )
+  }
+
+  // ウィンドウの座標(マウスの位置など)を、ゲームを描いている解像度の座標に直す
+  // 黒い帯の上ならNone
+  pub fn to_logical(&self, output: (u32, u32), point: Point) -> Option<Point> {
+    let letterbox = self.letterbox(output);
+    if !letterbox.contains_point(point) {
+      return None;
+    }
+    let scale = |offset: i32, size: u32, logical: u32| {
+      (i64::from(offset) * i64::from(logical) / i64::from(size)) as i32
+    };
+    Some(Point::new(
+      scale(
+        point.x() - letterbox.x(),
+        letterbox.width(),
+        self.logical_size.0,
+      ),
+      scale(
+        point.y() - letterbox.y(),
+        letterbox.height(),
+        self.logical_size.1,
+      ),
+    ))
+  }
+}
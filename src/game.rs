@@ -0,0 +1,221 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use specs::prelude::*;
+
+use crate::ai::{self, Behavior};
+use crate::assets::{AssetManager, TextureHandle};
+use crate::cavegen::{CaveSettings, CaveTiles};
+use crate::combat::{self, PlayerDefeated};
+use crate::components::*;
+use crate::input::InputState;
+use crate::movement::MovementTuning;
+use crate::tilemap::{TileId, TileMap, Tileset};
+use crate::time::{DeltaTime, SIMULATION_STEP};
+use crate::vector::Vec2;
+use crate::{animator, camera, hud, physics, player_input};
+
+// SDLのウィンドウやテクスチャに依存しない、ワールドとシステムの組み合わせ
+pub struct Game {
+  pub world: World,
+  dispatcher: Dispatcher<'static, 'static>,
+}
+
+impl Game {
+  pub fn new() -> Self {
+    // PlayerInputの依存関係を指定するのがあんまり分かってない
+    let mut dispatcher = DispatcherBuilder::new()
+      .with(player_input::PlayerInput, "PlayerInput", &[])
+      .with(ai::EnemyAi, "EnemyAi", &[])
+      .with(physics::Physics, "Physics", &["PlayerInput", "EnemyAi"])
+      .with(animator::Animator, "Animator", &["PlayerInput"])
+      .with(camera::CameraFollow, "CameraFollow", &["Physics"])
+      .with(combat::PlayerAttack, "PlayerAttack", &["Physics"])
+      .with(combat::Combat, "Combat", &["PlayerAttack"])
+      .with(combat::HitFlash::default(), "HitFlash", &["Combat"])
+      .with(combat::Expire, "Expire", &["Combat"])
+      .with(hud::HealthHud, "HealthHud", &["Combat"])
+      .build();
+
+    let mut world = World::new();
+    dispatcher.setup(&mut world.res);
+    // どのシステムも使わないコンポーネントは、setupでは登録されないのでここで登録する
+    // 読み込み直すために、ファイルの場所を覚えておくもの
+    world.register::<AnimationSource>();
+    world.register::<MovementSource>();
+    world.register::<BehaviorSource>();
+
+    world.add_resource(DeltaTime(SIMULATION_STEP));
+    world.add_resource(TileMap::default());
+    world.add_resource(AssetManager::default());
+
+    Self { world, dispatcher }
+  }
+
+  // InputStateに貯まった入力を使ってシミュレーションを1ステップ進める
+  pub fn tick(&mut self) {
+    self.dispatcher.dispatch(&self.world.res);
+    self.world.maintain();
+    self.world.write_resource::<InputState>().end_tick();
+  }
+
+  // 前のステップの位置を今の位置に揃える。シミュレーションを止めている間に呼んでおくと、
+  // 描くときの補間で、最後の2ステップの位置の間を行ったり来たりしなくなる
+  pub fn settle(&mut self) {
+    let positions = self.world.read_storage::<Position>();
+    let mut previous = self.world.write_storage::<PreviousPosition>();
+    for (pos, prev) in (&positions, &mut previous).join() {
+      prev.0 = pos.0;
+    }
+    self.world.write_resource::<camera::Camera>().settle();
+  }
+
+  // プレイヤーが倒れていればtrue
+  pub fn player_defeated(&self) -> bool {
+    self.world.read_resource::<PlayerDefeated>().0
+  }
+}
+
+impl Default for Game {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+// assets/cave_tiles.pngに並んでいるタイル
+pub const FLOOR_TILE: TileId = 0;
+pub const WALL_TILE: TileId = 1;
+pub const EXIT_TILE: TileId = 2;
+
+pub fn cave_tileset(spritesheet: TextureHandle) -> Tileset {
+  Tileset::from_grid(spritesheet, 32, 32, 3, &[false, true, false])
+}
+
+// cave_tilesetのタイルで洞窟を作る設定
+pub fn cave_settings(seed: u64) -> CaveSettings {
+  CaveSettings {
+    seed,
+    ..CaveSettings::new(CaveTiles {
+      floor: FLOOR_TILE,
+      wall: WALL_TILE,
+      exit: EXIT_TILE,
+    })
+  }
+}
+
+// 体力と、ダメージを受けたあとの無敵時間(秒)
+const PLAYER_HEALTH: u32 = 5;
+const PLAYER_INVULNERABILITY: f64 = 1.0;
+const ENEMY_HEALTH: u32 = 3;
+const ENEMY_INVULNERABILITY: f64 = 0.3;
+// Attackで向いている方向に出す攻撃。敵の無敵時間より長く出しておくと、1回で2度当たってしまう
+const PLAYER_ATTACK: MeleeAttack = MeleeAttack {
+  damage: Damage {
+    amount: 1,
+    knockback: 300.0,
+  },
+  reach: 20.0,
+  size: (28, 28),
+  duration: 0.15,
+};
+// 体に触れたときに与えるダメージ
+const ENEMY_DAMAGE: Damage = Damage {
+  amount: 1,
+  knockback: 360.0,
+};
+
+pub fn create_player(
+  world: &mut World,
+  animation: MovementAnimation,
+  tuning: MovementTuning,
+  position: Point,
+) -> Entity {
+  world
+    .create_entity()
+    .with(InputControlled)
+    .with(Position(position.into()))
+    .with(PreviousPosition(position.into()))
+    .with(Velocity::default())
+    .with(Movement::new(tuning))
+    // 足元だけを当たり判定にして、頭は壁に少し重なれるようにする
+    .with(Collider {
+      hitbox: Rect::new(-10, 0, 20, 14),
+      solid: true,
+    })
+    // 攻撃は体全体で受ける
+    .with(Health::new(PLAYER_HEALTH, PLAYER_INVULNERABILITY))
+    .with(Hurtbox {
+      rect: Rect::new(-10, -12, 20, 26),
+      team: Team::Player,
+    })
+    .with(PLAYER_ATTACK)
+    .with(animation.walk.right.frames[0].sprite.clone())
+    .with(animation)
+    .build()
+}
+
+// 見分けやすいように、敵はプレイヤーと同じスプライトを赤っぽく描く
+const ENEMY_TINT: Color = Color::RGB(255, 120, 120);
+
+// seedはうろつき方を決める乱数の種。敵ごとに変えておく
+pub fn create_enemy(
+  world: &mut World,
+  animation: MovementAnimation,
+  tuning: MovementTuning,
+  behavior: Behavior,
+  position: Point,
+  seed: u64,
+) -> Entity {
+  world
+    .create_entity()
+    .with(Ai::new(behavior, position.into(), seed))
+    .with(Position(position.into()))
+    .with(PreviousPosition(position.into()))
+    .with(Velocity::default())
+    .with(Movement::new(tuning))
+    .with(Collider {
+      hitbox: Rect::new(-10, 0, 20, 14),
+      solid: true,
+    })
+    .with(Health::new(ENEMY_HEALTH, ENEMY_INVULNERABILITY))
+    .with(Hurtbox {
+      rect: Rect::new(-10, -12, 20, 26),
+      team: Team::Enemy,
+    })
+    // 当たり判定どうしはめり込まないので、触れたときに届くように足元より一回り大きくする
+    .with(Hitbox {
+      rect: Rect::new(-14, -8, 28, 26),
+      team: Team::Enemy,
+    })
+    .with(ENEMY_DAMAGE)
+    .with(SpriteStyle {
+      tint: ENEMY_TINT,
+      ..SpriteStyle::default()
+    })
+    .with(animation.walk.right.frames[0].sprite.clone())
+    .with(animation)
+    .build()
+}
+
+// 画面の左上に出す体力の表示
+const HUD_POSITION: Vec2 = Vec2::new(8.0, 8.0);
+const HUD_COLOR: Color = Color::RGB(255, 255, 255);
+const HUD_TEXT_SIZE: u32 = 16;
+
+// targetの体力を画面の左上に表示するエンティティを作る
+pub fn create_health_display(world: &mut World, target: Entity) -> Entity {
+  let content = world
+    .read_storage::<Health>()
+    .get(target)
+    .map(hud::health_text)
+    .unwrap_or_default();
+  world
+    .create_entity()
+    .with(Position(HUD_POSITION))
+    .with(Text {
+      content,
+      color: HUD_COLOR,
+      size: HUD_TEXT_SIZE,
+    })
+    .with(HealthDisplay { target })
+    .build()
+}
@@ -0,0 +1,114 @@
+use sdl2::controller::{Axis, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+use std::collections::HashMap;
+
+use crate::input::{InputBindings, InputSource, InputState};
+
+struct Gamepad {
+  // 開いている間だけイベントが届くので、使わなくても持っておく
+  _controller: GameController,
+  // 左スティックの生の値
+  stick: (i16, i16),
+}
+
+// SDLのGameControllerを使った入力。抜き差しされたゲームパッドをその場で開いたり閉じたりする
+pub struct Gamepads {
+  subsystem: GameControllerSubsystem,
+  // joystickのinstance idごとのゲームパッド
+  pads: HashMap<u32, Gamepad>,
+}
+
+impl Gamepads {
+  // 起動時にすでにつながっているゲームパッドも、SDLがControllerDeviceAddedとして知らせてくれる
+  pub fn new(subsystem: GameControllerSubsystem) -> Self {
+    Self {
+      subsystem,
+      pads: HashMap::new(),
+    }
+  }
+
+  // ゲームパッドのイベントをInputStateに反映させる。それ以外のイベントは無視する
+  pub fn handle_event(&mut self, event: &Event, bindings: &InputBindings, input: &mut InputState) {
+    match *event {
+      Event::ControllerDeviceAdded { which, .. } => {
+        if !self.subsystem.is_game_controller(which) {
+          return;
+        }
+        match self.subsystem.open(which) {
+          Ok(controller) => {
+            let id = controller.instance_id();
+            self.pads.insert(
+              id,
+              Gamepad {
+                _controller: controller,
+                stick: (0, 0),
+              },
+            );
+          }
+          Err(e) => eprintln!("failed to open game controller {}: {}", which, e),
+        }
+      }
+      Event::ControllerDeviceRemoved { which, .. } => {
+        if self.pads.remove(&which).is_none() {
+          return;
+        }
+        // キーボードや他のゲームパッドでも押されている行動は押したままにする
+        input.release_sources(|source| matches!(source, InputSource::Button(id, _) if id == which));
+        // 抜かれたゲームパッドのスティックで歩き続けないようにする
+        self.update_analog_move(bindings, input);
+      }
+      Event::ControllerAxisMotion {
+        which, axis, value, ..
+      } => {
+        let pad = match self.pads.get_mut(&which) {
+          Some(pad) => pad,
+          None => return,
+        };
+        match axis {
+          Axis::LeftX => pad.stick.0 = value,
+          Axis::LeftY => pad.stick.1 = value,
+          _ => return,
+        }
+        self.update_analog_move(bindings, input);
+      }
+      Event::ControllerButtonDown { which, button, .. } if self.pads.contains_key(&which) => {
+        for &action in bindings.button_actions(button) {
+          input.press_from(InputSource::Button(which, button), action);
+        }
+      }
+      Event::ControllerButtonUp { which, button, .. } if self.pads.contains_key(&which) => {
+        for &action in bindings.button_actions(button) {
+          input.release_from(InputSource::Button(which, button), action);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  // つながっているゲームパッドのうち、一番大きく倒されているスティックの傾きをInputStateに入れる
+  fn update_analog_move(&self, bindings: &InputBindings, input: &mut InputState) {
+    let deadzone = bindings.settings.stick_deadzone;
+    let (x, y) = self
+      .pads
+      .values()
+      .map(|pad| apply_deadzone(pad.stick, deadzone))
+      .max_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)))
+      .unwrap_or((0.0, 0.0));
+    input.set_analog_move(x, y);
+  }
+}
+
+// スティックの生の値を長さ0.0..1.0のベクトルにする
+// デッドゾーンの内側は0にし、外側はデッドゾーンの端から1.0までを0.0..1.0に伸ばす
+fn apply_deadzone(stick: (i16, i16), deadzone: f64) -> (f64, f64) {
+  let x = f64::from(stick.0) / f64::from(i16::MAX);
+  let y = f64::from(stick.1) / f64::from(i16::MAX);
+  let magnitude = x.hypot(y);
+  if magnitude <= deadzone {
+    return (0.0, 0.0);
+  }
+
+  let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+  (x / magnitude * scaled, y / magnitude * scaled)
+}
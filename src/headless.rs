@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+
+use crate::game::Game;
+use crate::input::{Action, InputState};
+
+// 台本に書かれた1つの入力
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptInput {
+  Press(Action),
+  Release(Action),
+}
+
+// ヘッドレス実行で使う入力の台本
+// 1行に「tick番号 press|release 行動名」を書く。行動名はMoveLeftなどinput::Actionの名前
+// 空行と#から始まる行は読み飛ばす
+#[derive(Debug, Default)]
+pub struct Script {
+  inputs: Vec<(u64, ScriptInput)>,
+}
+
+impl Script {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let mut inputs = Vec::new();
+
+    for (line_number, line) in source.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let words: Vec<&str> = line.split_whitespace().collect();
+      let (tick, kind, name) = match words.as_slice() {
+        [tick, kind, name] => (tick, kind, name),
+        _ => {
+          return Err(format!(
+            "line {}: expected \"<tick> press|release <action>\"",
+            line_number + 1
+          ))
+        }
+      };
+
+      let tick = tick
+        .parse::<u64>()
+        .map_err(|_| format!("line {}: expected a tick number", line_number + 1))?;
+      let action = Action::from_name(name)
+        .ok_or_else(|| format!("line {}: unknown action {:?}", line_number + 1, name))?;
+      let input = match *kind {
+        "press" => ScriptInput::Press(action),
+        "release" => ScriptInput::Release(action),
+        other => {
+          return Err(format!(
+            "line {}: expected press or release, found {:?}",
+            line_number + 1,
+            other
+          ))
+        }
+      };
+
+      inputs.push((tick, input));
+    }
+
+    Ok(Self { inputs })
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let source = fs::read_to_string(path.as_ref())
+      .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+    Self::parse(&source)
+  }
+
+  // tickの直前に与える入力を、書かれた順に返す
+  pub fn inputs_at(&self, tick: u64) -> impl Iterator<Item = ScriptInput> + '_ {
+    self
+      .inputs
+      .iter()
+      .filter(move |(t, _)| *t == tick)
+      .map(|(_, input)| *input)
+  }
+}
+
+// SDLを使わずに、台本どおりの入力でticks回シミュレーションを進める
+pub fn run(game: &mut Game, ticks: u64, script: &Script) {
+  for tick in 0..ticks {
+    {
+      let mut state = game.world.write_resource::<InputState>();
+      for input in script.inputs_at(tick) {
+        match input {
+          ScriptInput::Press(action) => state.press(action),
+          ScriptInput::Release(action) => state.release(action),
+        }
+      }
+    }
+    game.tick();
+  }
+}
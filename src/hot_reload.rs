@@ -0,0 +1,120 @@
+use specs::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ai::Behavior;
+use crate::animation::AnimationDef;
+use crate::assets::AssetManager;
+use crate::components::*;
+use crate::movement::MovementTuning;
+
+// ファイルの更新時刻を見比べて、変更されたファイルを見つける
+// OSの通知に頼らないので、どの環境でも一時ディレクトリを使って同じように動く
+#[derive(Debug, Default)]
+pub struct PollingWatcher {
+  files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl PollingWatcher {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // すでに見張っているファイルなら何もしない
+  pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+    let path = path.as_ref();
+    if !self.files.contains_key(path) {
+      self.files.insert(path.to_path_buf(), modified(path));
+    }
+  }
+
+  // 前回呼んでから更新時刻が変わったファイルを返す
+  pub fn poll(&mut self) -> Vec<PathBuf> {
+    let mut changed = Vec::new();
+    for (path, last_modified) in &mut self.files {
+      let current = modified(path);
+      if current != *last_modified {
+        *last_modified = current;
+        // 書き込みの途中で消えているときは、次に現れたときに読み込む
+        if current.is_some() {
+          changed.push(path.clone());
+        }
+      }
+    }
+    changed
+  }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// pathから作った歩行アニメーションを持つエンティティに、読み込み直したアニメーションを入れ直す
+// 対象のエンティティがなければfalseを返す
+pub fn reload_animation(world: &mut World, path: &Path) -> Result<bool, String> {
+  let targets: Vec<Entity> = {
+    let entities = world.entities();
+    let sources = world.read_storage::<AnimationSource>();
+    (&entities, &sources)
+      .join()
+      .filter(|(_, source)| source.0 == path)
+      .map(|(entity, _)| entity)
+      .collect()
+  };
+  if targets.is_empty() {
+    return Ok(false);
+  }
+
+  let def = AnimationDef::load(path)?;
+  let mut animations = world.write_storage::<MovementAnimation>();
+  for entity in targets {
+    let animation = def.movement_animation(&mut world.write_resource::<AssetManager>())?;
+    animations
+      .insert(entity, animation)
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(true)
+}
+
+// pathから読み込んだ設定で動くエンティティの、Movementの設定を入れ替える
+// 今の速度と進みたい向きはそのまま。対象のエンティティがなければfalseを返す
+pub fn reload_movement(world: &mut World, path: &Path) -> Result<bool, String> {
+  let sources = world.read_storage::<MovementSource>();
+  let mut movements = world.write_storage::<Movement>();
+  let mut targets = (&sources, &mut movements)
+    .join()
+    .filter(|(source, _)| source.0 == path)
+    .map(|(_, movement)| movement)
+    .peekable();
+  if targets.peek().is_none() {
+    return Ok(false);
+  }
+
+  let tuning = MovementTuning::load(path)?;
+  for movement in targets {
+    movement.tuning = tuning;
+  }
+  Ok(true)
+}
+
+// pathから読み込んだ性格で動くエンティティの、Behaviorを入れ替える。今の状態はそのまま続ける
+pub fn reload_behavior(world: &mut World, path: &Path) -> Result<bool, String> {
+  let sources = world.read_storage::<BehaviorSource>();
+  let mut ais = world.write_storage::<Ai>();
+  let mut targets = (&sources, &mut ais)
+    .join()
+    .filter(|(source, _)| source.0 == path)
+    .map(|(_, ai)| ai)
+    .peekable();
+  if targets.peek().is_none() {
+    return Ok(false);
+  }
+
+  let behavior = Behavior::load(path)?;
+  for ai in targets {
+    ai.behavior = behavior;
+  }
+  Ok(true)
+}
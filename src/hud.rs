@@ -0,0 +1,33 @@
+use specs::prelude::*;
+
+use crate::components::*;
+
+// 体力の表示。"HP 3/5"のように書く
+pub fn health_text(health: &Health) -> String {
+  format!("HP {}/{}", health.current, health.max)
+}
+
+// HealthDisplayを持つエンティティのTextを、表示している相手の今の体力に合わせるシステム
+// 変わっていなければ書き直さないので、描く側は文字列が変わったときだけ作り直せばよい
+pub struct HealthHud;
+
+impl<'a> System<'a> for HealthHud {
+  type SystemData = (
+    ReadStorage<'a, HealthDisplay>,
+    ReadStorage<'a, Health>,
+    WriteStorage<'a, Text>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    for (display, text) in (&data.0, &mut data.2).join() {
+      // 相手が消えたときは、最後に表示していた体力のまま残す
+      let content = match data.1.get(display.target) {
+        Some(health) => health_text(health),
+        None => continue,
+      };
+      if text.content != content {
+        text.content = content;
+      }
+    }
+  }
+}
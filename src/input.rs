@@ -0,0 +1,268 @@
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use sdl2::rect::Point;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// キーやボタンに割り当てる、ゲームの中での行動
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Action {
+  MoveUp,
+  MoveDown,
+  MoveLeft,
+  MoveRight,
+  Attack,
+  Pause,
+  ToggleFullscreen,
+  Screenshot,
+}
+
+impl Action {
+  pub const ALL: [Action; 8] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Attack,
+    Action::Pause,
+    Action::ToggleFullscreen,
+    Action::Screenshot,
+  ];
+
+  // 設定ファイルやスクリプトに書く名前から行動を探す
+  pub fn from_name(name: &str) -> Option<Action> {
+    Action::ALL
+      .iter()
+      .copied()
+      .find(|action| format!("{:?}", action) == name)
+  }
+}
+
+// 行動を押しているもの。1つの行動に複数のキーやボタンを割り当てられるので、
+// 全部が離されるまでは行動を押したままにしておく
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputSource {
+  Key(Keycode),
+  // joystickのinstance idと、そのゲームパッドのボタン
+  Button(u32, Button),
+  // キーやボタンを通さずに押したもの(headlessのスクリプトなど)
+  Script,
+}
+
+// 行動ごとの入力の状態を表すリソース
+// systemは生のキーイベントではなく、これを読んで動く
+#[derive(Debug, Default)]
+pub struct InputState {
+  // 押されている行動を、押された順に並べる
+  held: Vec<Action>,
+  // 押されている行動ごとに、押しているキーやボタン
+  sources: HashMap<Action, Vec<InputSource>>,
+  // 前のステップから今のステップまでの間に押された/離された行動
+  pressed: HashSet<Action>,
+  released: HashSet<Action>,
+  // アナログスティックの傾き。デッドゾーンを除いて、長さは0.0..1.0
+  analog_move: (f64, f64),
+  // マウスカーソルの位置。ゲームを描いている解像度の座標で、ウィンドウの外や黒い帯の上ならNone
+  pointer: Option<Point>,
+}
+
+impl InputState {
+  pub fn press(&mut self, action: Action) {
+    self.press_from(InputSource::Script, action);
+  }
+
+  pub fn release(&mut self, action: Action) {
+    self.release_from(InputSource::Script, action);
+  }
+
+  // sourceでactionを押す。他のキーやボタンですでに押されていれば、押された記録は増やさない
+  pub fn press_from(&mut self, source: InputSource, action: Action) {
+    let sources = self.sources.entry(action).or_default();
+    if !sources.contains(&source) {
+      sources.push(source);
+    }
+    if !self.held.contains(&action) {
+      self.held.push(action);
+      self.pressed.insert(action);
+    }
+  }
+
+  // sourceでactionを離す。actionを押しているものが残っていなければ、actionを離す
+  pub fn release_from(&mut self, source: InputSource, action: Action) {
+    let sources = match self.sources.get_mut(&action) {
+      Some(sources) => sources,
+      None => return,
+    };
+    sources.retain(|&held| held != source);
+    if !sources.is_empty() {
+      return;
+    }
+    self.sources.remove(&action);
+    if let Some(i) = self.held.iter().position(|&held| held == action) {
+      self.held.remove(i);
+      self.released.insert(action);
+    }
+  }
+
+  // filterがtrueを返すキーやボタンを全部離す。ゲームパッドが抜かれたときに使う
+  pub fn release_sources<F: Fn(InputSource) -> bool>(&mut self, filter: F) {
+    let held: Vec<(InputSource, Action)> = self
+      .sources
+      .iter()
+      .flat_map(|(&action, sources)| sources.iter().map(move |&source| (source, action)))
+      .filter(|&(source, _)| filter(source))
+      .collect();
+    for (source, action) in held {
+      self.release_from(source, action);
+    }
+  }
+
+  // actionsのうち、押されている中で一番最後に押されたもの
+  pub fn most_recent(&self, actions: &[Action]) -> Option<Action> {
+    self
+      .held
+      .iter()
+      .rev()
+      .find(|action| actions.contains(action))
+      .copied()
+  }
+
+  pub fn is_held(&self, action: Action) -> bool {
+    self.held.contains(&action)
+  }
+
+  pub fn just_pressed(&self, action: Action) -> bool {
+    self.pressed.contains(&action)
+  }
+
+  pub fn just_released(&self, action: Action) -> bool {
+    self.released.contains(&action)
+  }
+
+  pub fn set_analog_move(&mut self, x: f64, y: f64) {
+    self.analog_move = (x, y);
+  }
+
+  // スティックが倒されていなければNone
+  pub fn analog_move(&self) -> Option<(f64, f64)> {
+    if self.analog_move == (0.0, 0.0) {
+      None
+    } else {
+      Some(self.analog_move)
+    }
+  }
+
+  pub fn set_pointer(&mut self, pointer: Option<Point>) {
+    self.pointer = pointer;
+  }
+
+  pub fn pointer(&self) -> Option<Point> {
+    self.pointer
+  }
+
+  // シミュレーションを1ステップ進めたあとに呼び、押された/離されたの記録を消す
+  pub fn end_tick(&mut self) {
+    self.pressed.clear();
+    self.released.clear();
+  }
+}
+
+// 入力をどう解釈するかの設定を表すリソース
+#[derive(Debug, Clone)]
+pub struct InputSettings {
+  // trueなら上下と左右を同時に押して斜めに歩ける
+  pub diagonal_movement: bool,
+  // アナログスティックの傾きがこの割合(0.0..1.0)より小さいときは倒されていないとみなす
+  pub stick_deadzone: f64,
+}
+
+impl Default for InputSettings {
+  fn default() -> Self {
+    Self {
+      diagonal_movement: false,
+      stick_deadzone: default_stick_deadzone(),
+    }
+  }
+}
+
+fn default_stick_deadzone() -> f64 {
+  0.25
+}
+
+// 設定ファイル(RON)の中身。例はassets/input.ronを参照
+#[derive(Debug, Deserialize)]
+struct BindingsDef {
+  // 行動ごとに、割り当てるキーの名前(SDLのキー名)を並べる
+  keys: HashMap<Action, Vec<String>>,
+  // 行動ごとに、割り当てるゲームパッドのボタンの名前(SDLのボタン名)を並べる
+  #[serde(default)]
+  buttons: HashMap<Action, Vec<String>>,
+  #[serde(default)]
+  diagonal_movement: bool,
+  #[serde(default = "default_stick_deadzone")]
+  stick_deadzone: f64,
+}
+
+// キーやボタンと行動の対応。1つの行動に複数のキーやボタンを割り当てられる
+#[derive(Debug, Default)]
+pub struct InputBindings {
+  keys: HashMap<Keycode, Vec<Action>>,
+  buttons: HashMap<Button, Vec<Action>>,
+  pub settings: InputSettings,
+}
+
+impl InputBindings {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let def: BindingsDef = ron::de::from_str(source).map_err(|e| e.to_string())?;
+
+    let mut bindings = Self::default();
+    bindings.settings.diagonal_movement = def.diagonal_movement;
+    bindings.settings.stick_deadzone = def.stick_deadzone;
+    for (action, names) in def.keys {
+      for name in names {
+        let keycode =
+          Keycode::from_name(&name).ok_or_else(|| format!("unknown key name {:?}", name))?;
+        bindings.bind(keycode, action);
+      }
+    }
+    for (action, names) in def.buttons {
+      for name in names {
+        let button =
+          Button::from_string(&name).ok_or_else(|| format!("unknown button name {:?}", name))?;
+        bindings.bind_button(button, action);
+      }
+    }
+
+    Ok(bindings)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  pub fn bind(&mut self, keycode: Keycode, action: Action) {
+    let actions = self.keys.entry(keycode).or_default();
+    if !actions.contains(&action) {
+      actions.push(action);
+    }
+  }
+
+  pub fn bind_button(&mut self, button: Button, action: Action) {
+    let actions = self.buttons.entry(button).or_default();
+    if !actions.contains(&action) {
+      actions.push(action);
+    }
+  }
+
+  pub fn actions(&self, keycode: Keycode) -> &[Action] {
+    self.keys.get(&keycode).map_or(&[], Vec::as_slice)
+  }
+
+  pub fn button_actions(&self, button: Button) -> &[Action] {
+    self.buttons.get(&button).map_or(&[], Vec::as_slice)
+  }
+}
@@ -0,0 +1,29 @@
+pub mod ai;
+pub mod animation;
+pub mod animator;
+pub mod assets;
+pub mod camera;
+pub mod cavegen;
+pub mod collision;
+pub mod combat;
+pub mod components;
+pub mod display;
+pub mod game;
+pub mod gamepad;
+pub mod headless;
+pub mod hot_reload;
+pub mod hud;
+pub mod input;
+pub mod movement;
+pub mod pathfinding;
+pub mod physics;
+pub mod player_input;
+pub mod renderer;
+pub mod scene;
+pub mod screenshot;
+pub mod spatial;
+pub mod text;
+pub mod tiled;
+pub mod tilemap;
+pub mod time;
+pub mod vector;
@@ -1,202 +1,495 @@
-mod animator;
-mod components;
-mod keyboard;
-mod physics;
-mod renderer;
-
-use crate::components::*;
-use sdl2::event::Event;
-use sdl2::image::{self, InitFlag, LoadTexture};
-use sdl2::keyboard::Keycode;
+use cave::ai::Behavior;
+use cave::animation::AnimationDef;
+use cave::assets::{AssetManager, TextureHandle, Textures};
+use cave::camera::Camera;
+use cave::cavegen::{self, Cave};
+use cave::combat::PlayerDefeated;
+use cave::components::*;
+use cave::display::DisplaySettings;
+use cave::game::{
+  cave_settings, cave_tileset, create_enemy, create_health_display, create_player, Game,
+};
+use cave::gamepad::Gamepads;
+use cave::headless::{self, Script};
+use cave::hot_reload::{reload_animation, reload_behavior, reload_movement, PollingWatcher};
+use cave::input::{Action, InputBindings, InputSource, InputState};
+use cave::movement::MovementTuning;
+use cave::renderer;
+use cave::scene::{Scene, SceneStack, Transition};
+use cave::screenshot;
+use cave::text::{BitmapFont, Fonts};
+use cave::tiled::TiledMap;
+use cave::tilemap::TileMap;
+use cave::time::{FixedTimestep, SIMULATION_STEP};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::image::{self, InitFlag};
 use sdl2::pixels::Color;
 use sdl2::rect::Point;
-use sdl2::rect::Rect;
+use sdl2::video::{FullscreenType, WindowContext};
 use specs::prelude::*;
-use std::time::Duration;
+use std::env;
+use std::fmt::Display;
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-pub enum MovementCommand {
-  Stop,
-  Move(Direction),
+const PLAYER_ANIMATION: &str = "assets/seeker.ron";
+const PLAYER_MOVEMENT: &str = "assets/player_movement.ron";
+const ENEMY_ANIMATION: &str = "assets/seeker.ron";
+const ENEMY_MOVEMENT: &str = "assets/enemy_movement.ron";
+const ENEMY_BEHAVIOR: &str = "assets/enemy_behavior.ron";
+const INPUT_BINDINGS: &str = "assets/input.ron";
+const DISPLAY_SETTINGS: &str = "assets/display.ron";
+const SCREENSHOT_DIR: &str = "screenshots";
+const BITMAP_FONT: &str = "assets/fonts/font5x7.ron";
+const CAVE_TILES: &str = "assets/cave_tiles.png";
+
+// 開発中にファイルの変更を確かめる間隔
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+// 変更されたテクスチャとアニメーション定義、動きと敵の性格の設定、Tiledのマップを読み込み直す
+// 読み込みに失敗しても、ゲームは止めずに前の内容のまま続ける
+// マップはタイルだけを入れ替え、オブジェクトから作ったエンティティはそのままにする
+fn hot_reload(
+  watcher: &mut PollingWatcher,
+  game: &mut Game,
+  textures: &mut Textures<WindowContext>,
+  map: Option<&str>,
+) {
+  if let Some(map) = map {
+    watcher.watch(map);
+  }
+  {
+    let assets = game.world.read_resource::<AssetManager>();
+    for path in assets.paths() {
+      watcher.watch(path);
+    }
+  }
+  {
+    let sources = game.world.read_storage::<AnimationSource>();
+    for source in (&sources).join() {
+      watcher.watch(&source.0);
+    }
+    let sources = game.world.read_storage::<MovementSource>();
+    for source in (&sources).join() {
+      watcher.watch(&source.0);
+    }
+    let sources = game.world.read_storage::<BehaviorSource>();
+    for source in (&sources).join() {
+      watcher.watch(&source.0);
+    }
+  }
+
+  for path in watcher.poll() {
+    let handle = game.world.read_resource::<AssetManager>().handle(&path);
+    let result = match handle {
+      Some(handle) => textures
+        .reload(&game.world.read_resource(), handle)
+        .map_err(|e| e.to_string()),
+      None if map.map(Path::new) == Some(path.as_path()) => {
+        TiledMap::load(&path, &mut game.world.write_resource())
+          .map(|tiled| *game.world.write_resource::<TileMap>() = tiled.map)
+      }
+      None => reload_animation(&mut game.world, &path)
+        .and_then(|_| reload_movement(&mut game.world, &path))
+        .and_then(|_| reload_behavior(&mut game.world, &path))
+        .map(|_| ()),
+    };
+    match result {
+      Ok(()) => println!("reloaded {}", path.display()),
+      Err(e) => eprintln!("failed to reload {}", e),
+    }
+  }
+}
+
+// 起動に必要なファイルが読めなかったときは、パニックせずにエラーを表示して終了する
+fn fail<E: Display>(e: E) -> ! {
+  eprintln!("{}", e);
+  process::exit(1);
 }
 
-fn direction_spritesheet_row(direction: Direction) -> i32 {
-  use self::Direction::*;
-  match direction {
-    Up => 3,
-    Down => 0,
-    Left => 1,
-    Right => 2,
+// Tiledのマップをリソースにし、オブジェクトをエンティティにする
+// プレイヤーと敵の出現位置を返す。マップにプレイヤーのオブジェクトがなければマップの中心
+fn load_tiled_map(game: &mut Game, path: &str) -> (Point, Vec<Point>) {
+  let tiled = TiledMap::load(path, &mut game.world.write_resource()).unwrap_or_else(|e| fail(e));
+  tiled.spawn_objects(&mut game.world);
+  let spawn = tiled.player_spawn().unwrap_or_else(|| {
+    let (width, height) = tiled.map.pixel_size();
+    Point::new(width as i32 / 2, height as i32 / 2)
+  });
+  let enemies = tiled.enemy_spawns();
+  game.world.add_resource(tiled.map);
+  (spawn, enemies)
+}
+
+// 敵をpointsに置く。うろつき方の乱数の種は置いた場所から決める
+fn spawn_enemies(game: &mut Game, points: &[Point]) {
+  let animation = AnimationDef::load(ENEMY_ANIMATION).unwrap_or_else(|e| fail(e));
+  let tuning = MovementTuning::load(ENEMY_MOVEMENT).unwrap_or_else(|e| fail(e));
+  let behavior = Behavior::load(ENEMY_BEHAVIOR).unwrap_or_else(|e| fail(e));
+  for &point in points {
+    let animation = animation
+      .movement_animation(&mut game.world.write_resource())
+      .unwrap_or_else(|e| fail(e));
+    let seed = (u64::from(point.x() as u32) << 32) | u64::from(point.y() as u32);
+    let enemy = create_enemy(&mut game.world, animation, tuning, behavior, point, seed);
+    game
+      .world
+      .write_storage()
+      .insert(enemy, AnimationSource(ENEMY_ANIMATION.into()))
+      .unwrap();
+    game
+      .world
+      .write_storage()
+      .insert(enemy, MovementSource(ENEMY_MOVEMENT.into()))
+      .unwrap();
+    game
+      .world
+      .write_storage()
+      .insert(enemy, BehaviorSource(ENEMY_BEHAVIOR.into()))
+      .unwrap();
+  }
+}
+
+// 使い方: cave --headless <ticks> [script] [--seed <seed>] [--map <path>] [--screenshot <path>]
+// ウィンドウを作らずにシミュレーションだけを進め、最後の状態を標準出力に書き出す
+// --mapを渡したときはそのマップ、--seedを渡したときはその洞窟の中で、どちらもなければ何もない空間で動かす
+// --screenshotを渡すと、最後の状態をソフトウェアレンダラーで描いてPNGに保存する
+fn run_headless(args: &[String], seed: Option<u64>, map: Option<&str>, screenshot: Option<&str>) {
+  let ticks = match args.first().and_then(|ticks| ticks.parse::<u64>().ok()) {
+    Some(ticks) => ticks,
+    None => {
+      eprintln!("usage: cave --headless <ticks> [script]");
+      process::exit(2);
+    }
+  };
+  let script = match args.get(1) {
+    Some(path) => Script::load(path).unwrap_or_else(|e| fail(e)),
+    None => Script::default(),
+  };
+
+  let mut game = Game::new();
+  let animation = AnimationDef::load(PLAYER_ANIMATION)
+    .and_then(|def| def.movement_animation(&mut game.world.write_resource()))
+    .unwrap_or_else(|e| fail(e));
+  let (spawn, enemies) = match (map, seed) {
+    (Some(map), _) => load_tiled_map(&mut game, map),
+    (None, Some(seed)) => {
+      let tiles = game
+        .world
+        .write_resource::<AssetManager>()
+        .load(CAVE_TILES)
+        .unwrap_or_else(|e| fail(e));
+      let cave = generate_cave(seed, tiles);
+      let points = (cave.spawn_point(), cave.enemy_points());
+      game.world.add_resource(cave.map);
+      points
+    }
+    (None, None) => (Point::new(0, 0), Vec::new()),
+  };
+  spawn_enemies(&mut game, &enemies);
+  let tuning = MovementTuning::load(PLAYER_MOVEMENT).unwrap_or_else(|e| fail(e));
+  let player = create_player(&mut game.world, animation, tuning, spawn);
+  if screenshot.is_some() {
+    renderer::SystemData::setup(&mut game.world.res);
+    let display = DisplaySettings::load(DISPLAY_SETTINGS).unwrap_or_else(|e| fail(e));
+    let mut camera = game.world.write_resource::<Camera>();
+    camera.viewport = display.logical_size;
+    camera.target = Some(player);
+    camera.look_at(spawn);
+  }
+
+  headless::run(&mut game, ticks, &script);
+
+  if let Some(path) = screenshot {
+    let font =
+      BitmapFont::load(BITMAP_FONT, &mut game.world.write_resource()).unwrap_or_else(|e| fail(e));
+    screenshot::capture(
+      &game.world,
+      &mut Fonts::new(font),
+      Color::RGB(0, 64, 255),
+      path,
+    )
+    .unwrap_or_else(|e| fail(e));
+  }
+
+  let positions = game.world.read_storage::<Position>();
+  let sprites = game.world.read_storage::<Sprite>();
+  for (pos, sprite) in (&positions, &sprites).join() {
+    println!("{:?} {:?}", pos, sprite);
   }
 }
 
-fn character_animation_frames(
-  spritesheet: usize,
-  top_left_frame: Rect,
-  direction: Direction,
-) -> Vec<Sprite> {
-  let (frame_width, frame_height) = top_left_frame.size();
-  let y_offset = top_left_frame.y() + frame_height as i32 * direction_spritesheet_row(direction);
-
-  let mut frames = Vec::new();
-  for i in 0..3 {
-    frames.push(Sprite {
-      spritesheet,
-      region: Rect::new(
-        top_left_frame.x() + frame_width as i32 * i,
-        y_offset,
-        frame_width,
-        frame_height,
-      ),
-    })
+// argsからnameとその次の値を取り除いて、値を返す
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+  let i = args.iter().position(|arg| arg == name)?;
+  args.remove(i);
+  if i < args.len() {
+    Some(args.remove(i))
+  } else {
+    fail(format!("{} needs a value", name))
   }
+}
+
+// argsからnameを取り除いて、あったかどうかを返す
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+  let len = args.len();
+  args.retain(|arg| arg != name);
+  args.len() != len
+}
+
+// 前のゲームのエンティティを消して、洞窟(--mapを渡したときはそのマップ)とプレイヤー、敵を作り直す
+fn start_game(game: &mut Game, map: Option<&str>, seed: Option<u64>) {
+  game.world.delete_all();
+  game.world.maintain();
+  game.world.add_resource(PlayerDefeated::default());
+
+  let (player_animation, tiles) = {
+    let mut assets = game.world.write_resource::<AssetManager>();
+    let player_animation = AnimationDef::load(PLAYER_ANIMATION)
+      .and_then(|def| def.movement_animation(&mut assets))
+      .unwrap_or_else(|e| fail(e));
+    let tiles = assets.load(CAVE_TILES).unwrap_or_else(|e| fail(e));
+    (player_animation, tiles)
+  };
+
+  let (spawn, enemies) = match map {
+    Some(map) => load_tiled_map(game, map),
+    None => {
+      let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map_or(0, |time| time.as_nanos() as u64)
+      });
+      println!("cave seed: {}", seed);
+      let cave = generate_cave(seed, tiles);
+      let points = (cave.spawn_point(), cave.enemy_points());
+      game.world.add_resource(cave.map);
+      points
+    }
+  };
+
+  spawn_enemies(game, &enemies);
+  let tuning = MovementTuning::load(PLAYER_MOVEMENT).unwrap_or_else(|e| fail(e));
+  let player = create_player(&mut game.world, player_animation, tuning, spawn);
+  game
+    .world
+    .write_storage()
+    .insert(player, AnimationSource(PLAYER_ANIMATION.into()))
+    .unwrap();
+  game
+    .world
+    .write_storage()
+    .insert(player, MovementSource(PLAYER_MOVEMENT.into()))
+    .unwrap();
+  create_health_display(&mut game.world, player);
+  let mut camera = game.world.write_resource::<Camera>();
+  camera.target = Some(player);
+  camera.look_at(spawn);
+}
+
+// スクリーンショットの保存先。撮った時刻で名前をつける
+fn screenshot_path() -> String {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |time| time.as_millis());
+  format!("{}/cave-{}.png", SCREENSHOT_DIR, now)
+}
 
-  frames
+fn generate_cave(seed: u64, tiles: TextureHandle) -> Cave {
+  cavegen::generate(&cave_settings(seed), cave_tileset(tiles))
 }
 
 fn main() {
+  let mut args: Vec<String> = env::args().skip(1).collect();
+  // --seedで洞窟の形を決める。同じシードなら同じ洞窟になるので、不具合の報告に添えてもらう
+  let seed = take_option(&mut args, "--seed").map(|seed| {
+    seed
+      .parse::<u64>()
+      .unwrap_or_else(|_| fail(format!("invalid seed {:?}", seed)))
+  });
+  // --devをつけると、assets以下のファイルを書き換えたときに実行したまま読み込み直す
+  let dev_mode = take_flag(&mut args, "--dev");
+  // --mapでTiledのマップ(.tmx/.tmj)を渡すと、洞窟を作る代わりにそれを読み込む
+  let map = take_option(&mut args, "--map");
+  let screenshot = take_option(&mut args, "--screenshot");
+  if args.first().map(String::as_str) == Some("--headless") {
+    run_headless(&args[1..], seed, map.as_deref(), screenshot.as_deref());
+    return;
+  }
+
   let sdl_context = sdl2::init().unwrap();
   let video_subsystem = sdl_context.video().unwrap();
+  let mut gamepads = Gamepads::new(sdl_context.game_controller().unwrap());
   let _image_context = image::init(InitFlag::PNG | InitFlag::JPG).unwrap();
 
-  let window = video_subsystem
-    .window("SDL", 640, 480)
-    .position_centered()
-    .build()
-    .unwrap();
+  let display = DisplaySettings::load(DISPLAY_SETTINGS).unwrap_or_else(|e| fail(e));
+  let mut window_builder =
+    video_subsystem.window(&display.title, display.window_size.0, display.window_size.1);
+  window_builder.position_centered();
+  if display.resizable {
+    window_builder.resizable();
+  }
+  if display.fullscreen {
+    window_builder.fullscreen_desktop();
+  }
+  let window = window_builder.build().unwrap();
 
-  let mut canvas = window.into_canvas().build().unwrap();
+  // 描画の頻度はモニタのリフレッシュレートに任せ、シミュレーションの刻み幅とは切り離す
+  let mut canvas = window.into_canvas().present_vsync().build().unwrap();
   let texture_creator = canvas.texture_creator();
+  // ゲームはいったんこのテクスチャに論理解像度で描き、ウィンドウに合わせて拡大して写す
+  let mut frame = texture_creator
+    .create_texture_target(None, display.logical_size.0, display.logical_size.1)
+    .unwrap_or_else(|e| fail(e));
 
-  // Keyboardの依存関係を指定するのがあんまり分かってない
-  let mut dispatcher = DispatcherBuilder::new()
-    .with(keyboard::Keyboard, "Keyboard", &[])
-    .with(physics::Physics, "Physics", &["Keyboard"])
-    .with(animator::Animator, "Animator", &["Keyboard"])
-    .build();
-
-  let mut world = World::new();
-  dispatcher.setup(&mut world.res);
-  renderer::SystemData::setup(&mut world.res);
-
-  let movement_command: Option<MovementCommand> = None;
-  world.add_resource(movement_command);
-
-  let textures = [texture_creator.load_texture("assets/seeker.png").unwrap()];
-
-  let player_spritesheet = 0;
-  let player_top_left_frame = Rect::new(0, 0, 32, 32);
-  let player_animation = MovementAnimation {
-    current_frame: 0,
-    up_frames: character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Up),
-    down_frames: character_animation_frames(
-      player_spritesheet,
-      player_top_left_frame,
-      Direction::Down,
-    ),
-    left_frames: character_animation_frames(
-      player_spritesheet,
-      player_top_left_frame,
-      Direction::Left,
-    ),
-    right_frames: character_animation_frames(
-      player_spritesheet,
-      player_top_left_frame,
-      Direction::Right,
-    ),
-  };
+  let mut game = Game::new();
+  renderer::SystemData::setup(&mut game.world.res);
+  game.world.write_resource::<Camera>().viewport = display.logical_size;
+  let mut textures = Textures::new(&texture_creator);
+  #[cfg(feature = "ttf")]
+  let ttf_context = sdl2::ttf::init().unwrap();
+  let font =
+    BitmapFont::load(BITMAP_FONT, &mut game.world.write_resource()).unwrap_or_else(|e| fail(e));
+  let mut fonts = Fonts::new(font);
+  #[cfg(feature = "ttf")]
+  {
+    if let Some(path) = &display.ttf_font {
+      match cave::text::TtfFont::load(&ttf_context, path) {
+        Ok(font) => fonts.set_ttf(font),
+        Err(e) => eprintln!("{}; falling back to the bitmap font", e),
+      }
+    }
+  }
+  let mut scenes = SceneStack::new(Scene::Title);
 
-  world
-    .create_entity()
-    .with(KeyboardControlled)
-    .with(Position(Point::new(0, 0)))
-    .with(Velocity {
-      speed: 0,
-      direction: Direction::Right,
-    })
-    .with(player_animation.right_frames[0].clone())
-    .with(player_animation)
-    .build();
+  let bindings = InputBindings::load(INPUT_BINDINGS).unwrap();
+  game.world.add_resource(bindings.settings.clone());
 
   let mut event_pump = sdl_context.event_pump().unwrap();
   let mut i = 0;
+  let mut timestep = FixedTimestep::new(SIMULATION_STEP);
+  let mut watcher = if dev_mode {
+    Some(PollingWatcher::new())
+  } else {
+    None
+  };
+  let mut last_poll = Instant::now();
+  let mut take_screenshot = false;
   'running: loop {
-    let mut movement_command = None;
+    if let Some(watcher) = &mut watcher {
+      if last_poll.elapsed() >= HOT_RELOAD_INTERVAL {
+        last_poll = Instant::now();
+        hot_reload(watcher, &mut game, &mut textures, map.as_deref());
+      }
+    }
 
     for event in event_pump.poll_iter() {
       match event {
-        Event::Quit { .. }
-        | Event::KeyDown {
-          keycode: Some(Keycode::Escape),
-          ..
-        } => break 'running,
-        Event::KeyDown {
-          keycode: Some(Keycode::Left),
-          repeat: false,
-          ..
-        } => {
-          movement_command = Some(MovementCommand::Move(Direction::Left));
-        }
-        Event::KeyDown {
-          keycode: Some(Keycode::Right),
-          repeat: false,
-          ..
-        } => {
-          movement_command = Some(MovementCommand::Move(Direction::Right));
-        }
-        Event::KeyDown {
-          keycode: Some(Keycode::Up),
-          repeat: false,
-          ..
-        } => {
-          movement_command = Some(MovementCommand::Move(Direction::Up));
-        }
+        Event::Quit { .. } => break 'running,
         Event::KeyDown {
-          keycode: Some(Keycode::Down),
+          keycode: Some(keycode),
           repeat: false,
           ..
         } => {
-          movement_command = Some(MovementCommand::Move(Direction::Down));
+          let mut input = game.world.write_resource::<InputState>();
+          for &action in bindings.actions(keycode) {
+            input.press_from(InputSource::Key(keycode), action);
+          }
         }
         Event::KeyUp {
-          keycode: Some(Keycode::Left),
+          keycode: Some(keycode),
           repeat: false,
           ..
+        } => {
+          let mut input = game.world.write_resource::<InputState>();
+          for &action in bindings.actions(keycode) {
+            input.release_from(InputSource::Key(keycode), action);
+          }
         }
-        | Event::KeyUp {
-          keycode: Some(Keycode::Right),
-          repeat: false,
-          ..
+        Event::MouseMotion { x, y, .. } => {
+          let output = canvas.output_size().unwrap();
+          game
+            .world
+            .write_resource::<InputState>()
+            .set_pointer(display.to_logical(output, Point::new(x, y)));
         }
-        | Event::KeyUp {
-          keycode: Some(Keycode::Up),
-          repeat: false,
+        Event::Window {
+          win_event: WindowEvent::Leave,
           ..
-        }
-        | Event::KeyUp {
-          keycode: Some(Keycode::Down),
-          repeat: false,
-          ..
-        } => {
-          movement_command = Some(MovementCommand::Stop);
-        }
-        _ => {}
+        } => game.world.write_resource::<InputState>().set_pointer(None),
+        _ => gamepads.handle_event(
+          &event,
+          &bindings,
+          &mut game.world.write_resource::<InputState>(),
+        ),
       }
     }
 
-    *world.write_resource() = movement_command;
-
-    i = (i + 1) % 255;
-    dispatcher.dispatch(&mut world.res);
-    world.maintain();
+    timestep.advance();
+    while timestep.consume_step() {
+      if game
+        .world
+        .read_resource::<InputState>()
+        .just_pressed(Action::ToggleFullscreen)
+      {
+        let window = canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+          FullscreenType::Off => FullscreenType::Desktop,
+          _ => FullscreenType::Off,
+        };
+        window
+          .set_fullscreen(fullscreen)
+          .unwrap_or_else(|e| eprintln!("{}", e));
+      }
+      take_screenshot |= game
+        .world
+        .read_resource::<InputState>()
+        .just_pressed(Action::Screenshot);
+      // タイトルから遊び始めるときは、毎回新しい洞窟を作る
+      if scenes.update(&mut game) == Transition::Switch(Scene::Playing) {
+        start_game(&mut game, map.as_deref(), seed);
+      }
+      if scenes.is_empty() {
+        break 'running;
+      }
+      i = (i + 1) % 255;
+    }
 
-    renderer::render(
-      &mut canvas,
-      Color::RGB(i, 64, 255 - i),
-      &textures,
-      world.system_data(),
-    )
-    .unwrap();
+    // 実行中にAssetManagerへ登録されたテクスチャもここで読み込まれる
+    textures
+      .sync(&game.world.read_resource::<AssetManager>())
+      .unwrap_or_else(|e| fail(e));
+    let mut result = Ok(());
+    canvas
+      .with_texture_canvas(&mut frame, |frame_canvas| {
+        result = scenes.render(
+          frame_canvas,
+          &mut textures,
+          &mut fonts,
+          timestep.alpha(),
+          &game.world,
+          Color::RGB(i, 64, 255 - i),
+        );
+        // 拡大する前の、論理解像度の画面を保存する
+        if take_screenshot && result.is_ok() {
+          take_screenshot = false;
+          let path = screenshot_path();
+          match screenshot::save_png(frame_canvas, &path) {
+            Ok(()) => println!("saved {}", path),
+            Err(e) => eprintln!("failed to save a screenshot: {}", e),
+          }
+        }
+      })
+      .unwrap();
+    result.unwrap();
+    textures.end_frame();
 
-    ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 20));
+    // ウィンドウの大きさが変わっていても、縦横比を保って真ん中に写す
+    let letterbox = display.letterbox(canvas.output_size().unwrap());
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    canvas.copy(&frame, None, letterbox).unwrap();
+    canvas.present();
   }
 }
@@ -0,0 +1,78 @@
+use serde::Deserialize;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::vector::Vec2;
+
+// 動きの手触りを決める値。設定ファイル(RON)から読み込む。例はassets/player_movement.ronを参照
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct MovementTuning {
+  // 一番速いときに1秒あたりに進むピクセル数
+  pub max_speed: f64,
+  // 歩こうとしているときに1秒あたりに増やす速さ
+  pub acceleration: f64,
+  // 止まろうとしているとき、または速すぎるときに1秒あたりに減らす速さ
+  pub deceleration: f64,
+  // 1秒あたりに向きを変えられる角度(度)。0以下ならすぐに向きを変える
+  pub turn_rate: f64,
+}
+
+impl MovementTuning {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    ron::de::from_str(source).map_err(|e| e.to_string())
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  // velocityをdt秒だけdesiredに近づけた速度
+  // desiredは進みたい向きで、長さ(0.0..1.0)はmax_speedに対する割合
+  pub fn steer(&self, velocity: Vec2, desired: Vec2, dt: f64) -> Vec2 {
+    let speed = velocity.length();
+    let strength = desired.length().min(1.0);
+    if strength == 0.0 {
+      // 進みたい向きがなければ、向きはそのままで止まるまで減速する
+      let speed = (speed - self.deceleration * dt).max(0.0);
+      return velocity.normalized() * speed;
+    }
+
+    let direction = if speed == 0.0 || self.turn_rate <= 0.0 {
+      desired.normalized()
+    } else {
+      rotate_toward(
+        velocity.normalized(),
+        desired.normalized(),
+        self.turn_rate.to_radians() * dt,
+      )
+    };
+
+    let target_speed = self.max_speed * strength;
+    let speed = if speed < target_speed {
+      (speed + self.acceleration * dt).min(target_speed)
+    } else {
+      (speed - self.deceleration * dt).max(target_speed)
+    };
+    direction * speed
+  }
+}
+
+// 長さ1のfromを、最大max_angle(ラジアン)だけtoの方へ回す
+fn rotate_toward(from: Vec2, to: Vec2, max_angle: f64) -> Vec2 {
+  let current = from.y.atan2(from.x);
+  let mut difference = to.y.atan2(to.x) - current;
+  if difference > PI {
+    difference -= 2.0 * PI;
+  } else if difference < -PI {
+    difference += 2.0 * PI;
+  }
+  if difference.abs() <= max_angle {
+    return to;
+  }
+
+  let angle = current + max_angle.copysign(difference);
+  Vec2::new(angle.cos(), angle.sin())
+}
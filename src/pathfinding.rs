@@ -0,0 +1,152 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::tilemap::TileMap;
+use crate::vector::Vec2;
+
+// タイルの座標
+pub type Tile = (i32, i32);
+
+// A*で調べるマスの数の上限。行き着けない場所を探して、広いマップを全部調べ回らないようにする
+const MAX_SEARCH_NODES: usize = 4096;
+
+// 上下左右に1マス進むコストと、斜めに1マス進むコスト(およそ√2倍)
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+  (0, -1),
+  (0, 1),
+  (-1, 0),
+  (1, 0),
+  (-1, -1),
+  (1, -1),
+  (-1, 1),
+  (1, 1),
+];
+
+// startからgoalまで壁を避けて歩くタイルの列をA*で探す。startは含まず、goalは含む
+// 斜めにも進めるが、壁の角をかすめるような斜めの移動はしない
+// 行き着けないとき、または調べるマスが多すぎるときはNone
+pub fn find_path(map: &TileMap, start: Tile, goal: Tile) -> Option<Vec<Tile>> {
+  if map.is_solid(goal.0, goal.1) {
+    return None;
+  }
+
+  let mut open = BinaryHeap::new();
+  let mut cost: HashMap<Tile, u32> = HashMap::new();
+  let mut came_from: HashMap<Tile, Tile> = HashMap::new();
+  cost.insert(start, 0);
+  open.push(Reverse((estimate(start, goal), 0, start)));
+
+  let mut searched = 0;
+  while let Some(Reverse((_, current_cost, current))) = open.pop() {
+    if current == goal {
+      let mut path = Vec::new();
+      let mut tile = current;
+      while tile != start {
+        path.push(tile);
+        tile = came_from[&tile];
+      }
+      path.reverse();
+      return Some(path);
+    }
+    // もっと安く来られる道がすでに見つかっていれば、古い候補は捨てる
+    if cost.get(&current).is_some_and(|&best| current_cost > best) {
+      continue;
+    }
+    searched += 1;
+    if searched > MAX_SEARCH_NODES {
+      return None;
+    }
+
+    for &(dx, dy) in NEIGHBOURS.iter() {
+      let next = (current.0 + dx, current.1 + dy);
+      if map.is_solid(next.0, next.1) {
+        continue;
+      }
+      let diagonal = dx != 0 && dy != 0;
+      if diagonal
+        && (map.is_solid(current.0 + dx, current.1) || map.is_solid(current.0, current.1 + dy))
+      {
+        continue;
+      }
+
+      let step_cost = if diagonal {
+        DIAGONAL_COST
+      } else {
+        STRAIGHT_COST
+      };
+      let next_cost = current_cost + step_cost;
+      if cost.get(&next).is_none_or(|&best| next_cost < best) {
+        cost.insert(next, next_cost);
+        came_from.insert(next, current);
+        open.push(Reverse((next_cost + estimate(next, goal), next_cost, next)));
+      }
+    }
+  }
+  None
+}
+
+// 斜めにも進めるときの、壁がないとした場合のコスト
+fn estimate(from: Tile, to: Tile) -> u32 {
+  let (dx, dy) = (
+    (to.0 - from.0).unsigned_abs(),
+    (to.1 - from.1).unsigned_abs(),
+  );
+  let diagonal = dx.min(dy);
+  diagonal * DIAGONAL_COST + (dx.max(dy) - diagonal) * STRAIGHT_COST
+}
+
+// fromからtoまでの線分が壁のタイルを通らないかどうか
+pub fn line_of_sight(map: &TileMap, from: Vec2, to: Vec2) -> bool {
+  let (width, height) = (
+    f64::from(map.tileset.tile_width),
+    f64::from(map.tileset.tile_height),
+  );
+  if width == 0.0 || height == 0.0 {
+    return true;
+  }
+
+  // 線分が通るタイルを順にたどる
+  let mut tile = (
+    (from.x / width).floor() as i32,
+    (from.y / height).floor() as i32,
+  );
+  let goal = (
+    (to.x / width).floor() as i32,
+    (to.y / height).floor() as i32,
+  );
+  let direction = to - from;
+  let step = |d: f64| if d > 0.0 { 1 } else { -1 };
+  let (step_x, step_y) = (step(direction.x), step(direction.y));
+  // 線分の長さを1としたときの、次のタイルの境界までの割合と、タイル1つ分を横切る割合
+  let boundary = |tile: i32, step: i32, origin: f64, d: f64, size: f64| {
+    if d == 0.0 {
+      f64::INFINITY
+    } else {
+      let edge = f64::from(if step > 0 { tile + 1 } else { tile }) * size;
+      (edge - origin) / d
+    }
+  };
+  let mut next_x = boundary(tile.0, step_x, from.x, direction.x, width);
+  let mut next_y = boundary(tile.1, step_y, from.y, direction.y, height);
+  let delta_x = (width / direction.x).abs();
+  let delta_y = (height / direction.y).abs();
+
+  loop {
+    if map.is_solid(tile.0, tile.1) {
+      return false;
+    }
+    if tile == goal || next_x.min(next_y) > 1.0 {
+      return true;
+    }
+    if next_x < next_y {
+      tile.0 += step_x;
+      next_x += delta_x;
+    } else {
+      tile.1 += step_y;
+      next_y += delta_y;
+    }
+  }
+}
@@ -1,30 +1,185 @@
+use sdl2::rect::Rect;
+use shrev::EventChannel;
 use specs::prelude::*;
 
+use crate::collision::{self, Axis, Body, Collided, CollisionEvent};
 use crate::components::*;
+use crate::spatial::SpatialHash;
+use crate::tilemap::TileMap;
+use crate::time::DeltaTime;
+use crate::vector::Vec2;
 
 pub struct Physics;
 
 impl<'a> System<'a> for Physics {
-  type SystemData = (WriteStorage<'a, Position>, ReadStorage<'a, Velocity>);
+  type SystemData = (
+    Entities<'a>,
+    Read<'a, DeltaTime>,
+    ReadExpect<'a, TileMap>,
+    Write<'a, EventChannel<CollisionEvent>>,
+    WriteStorage<'a, Position>,
+    WriteStorage<'a, PreviousPosition>,
+    WriteStorage<'a, Velocity>,
+    ReadStorage<'a, Collider>,
+    WriteStorage<'a, Movement>,
+    Write<'a, SpatialHash>,
+  );
 
   fn run(&mut self, mut data: Self::SystemData) {
-    use self::Direction::*;
+    let dt = (data.1).0;
+    let map = &*data.2;
 
-    for (pos, vel) in (&mut data.0, &data.1).join() {
-      match vel.direction {
-        Left => {
-          pos.0 = pos.0.offset(-vel.speed, 0);
+    // 動く前の当たり判定をSpatialHashに入れておき、誰かが動くたびに更新する
+    let bodies = &mut *data.9;
+    bodies.clear();
+    for (entity, pos, collider) in (&data.0, &data.4, &data.7).join() {
+      bodies.insert(entity, collider.world_rect(pos.0));
+    }
+    let colliders = &data.7;
+
+    let mut events = Vec::new();
+
+    for (entity, pos, prev, vel, collider, movement) in (
+      &data.0,
+      &mut data.4,
+      (&mut data.5).maybe(),
+      &mut data.6,
+      colliders.maybe(),
+      (&mut data.8).maybe(),
+    )
+      .join()
+    {
+      if let Some(prev) = prev {
+        prev.0 = pos.0;
+      }
+
+      // 自分で歩くエンティティは、進みたい向きへ加速・減速してから動く
+      // 弾き飛ばされている間は、押し戻されないように速度をそのままにする
+      if let Some(movement) = movement {
+        if movement.stunned > 0.0 {
+          movement.stunned = (movement.stunned - dt).max(0.0);
+        } else {
+          vel.0 = movement.tuning.steer(vel.0, movement.desired, dt);
         }
-        Right => {
-          pos.0 = pos.0.offset(vel.speed, 0);
+      }
+
+      // 速度は1秒あたりに進むピクセル数
+      let delta = vel.0 * dt;
+
+      let collider = match collider {
+        Some(collider) => collider,
+        None => {
+          pos.0 += delta;
+          continue;
         }
-        Up => {
-          pos.0 = pos.0.offset(0, -vel.speed);
+      };
+
+      // 軸ごとに移動を止めることで、斜めに壁へ当たったときは壁に沿って滑る
+      let mut blockers = Vec::new();
+      // ぶつかった軸の速度は0にして、壁に向かって速さを溜め込まないようにする
+      if collider.solid {
+        // 動く前から動いたあとまでの範囲にいる相手だけを調べる
+        let reach = collider
+          .world_rect(pos.0)
+          .union(collider.world_rect(pos.0 + delta));
+        let nearby = nearby_bodies(bodies, colliders, reach);
+        let hit = move_axis(
+          entity,
+          collider,
+          &mut pos.0,
+          Axis::Horizontal,
+          delta.x,
+          map,
+          &nearby,
+        );
+        if !hit.is_empty() {
+          vel.0.x = 0.0;
         }
-        Down => {
-          pos.0 = pos.0.offset(0, vel.speed);
+        blockers.extend(hit);
+
+        let hit = move_axis(
+          entity,
+          collider,
+          &mut pos.0,
+          Axis::Vertical,
+          delta.y,
+          map,
+          &nearby,
+        );
+        if !hit.is_empty() {
+          vel.0.y = 0.0;
         }
+        blockers.extend(hit);
+      } else {
+        pos.0 += delta;
       }
+
+      let rect = collider.world_rect(pos.0);
+      bodies.update(entity, rect);
+
+      // すり抜けられる当たり判定とは重なったときにイベントだけを送る
+      let overlapping = nearby_bodies(bodies, colliders, rect)
+        .into_iter()
+        .filter(|body| body.entity != entity && (!body.solid || !collider.solid))
+        .map(|body| Collided::Entity(body.entity));
+
+      events.extend(
+        blockers
+          .into_iter()
+          .chain(overlapping)
+          .map(|other| CollisionEvent { entity, other }),
+      );
     }
+
+    data.3.iter_write(events);
+  }
+}
+
+// 当たり判定はピクセル単位なので、丸めた位置が何ピクセル動くかで壁や他のエンティティとぶつかるかを調べる
+// ぶつからなければ小数のまま動かし、ぶつかったら止まったピクセルに合わせる
+fn move_axis(
+  entity: Entity,
+  collider: &Collider,
+  position: &mut Vec2,
+  axis: Axis,
+  delta: f64,
+  map: &TileMap,
+  bodies: &[Body],
+) -> Vec<Collided> {
+  let current = match axis {
+    Axis::Horizontal => position.x,
+    Axis::Vertical => position.y,
+  };
+  let target = current + delta;
+  let distance = target.round() as i32 - current.round() as i32;
+  let hitbox = collider.world_rect(*position);
+  let (allowed, hit) = collision::sweep(entity, hitbox, axis, distance, map, bodies);
+
+  let moved = if hit.is_empty() {
+    target
+  } else {
+    current.round() + f64::from(allowed)
+  };
+  match axis {
+    Axis::Horizontal => position.x = moved,
+    Axis::Vertical => position.y = moved,
   }
+  hit
+}
+
+// rectと重なる当たり判定を、SpatialHashに入っている今の位置で集める
+fn nearby_bodies(bodies: &SpatialHash, colliders: &ReadStorage<Collider>, rect: Rect) -> Vec<Body> {
+  bodies
+    .query_rect(rect)
+    .into_iter()
+    .filter_map(|entity| {
+      let collider = colliders.get(entity)?;
+      let rect = bodies.rect(entity)?;
+      Some(Body {
+        entity,
+        rect,
+        solid: collider.solid,
+      })
+    })
+    .collect()
 }
@@ -0,0 +1,81 @@
+use crate::components::*;
+use crate::input::{Action, InputSettings, InputState};
+use crate::vector::Vec2;
+use specs::prelude::*;
+
+const MOVEMENT_ACTIONS: [Action; 4] = [
+  Action::MoveUp,
+  Action::MoveDown,
+  Action::MoveLeft,
+  Action::MoveRight,
+];
+
+// キーボードとゲームパッドのどちらから来た入力でも、InputStateを読んで操作する
+pub struct PlayerInput;
+
+impl<'a> System<'a> for PlayerInput {
+  type SystemData = (
+    Read<'a, InputState>,
+    Read<'a, InputSettings>,
+    ReadStorage<'a, InputControlled>,
+    WriteStorage<'a, Movement>,
+  );
+
+  fn run(&mut self, mut data: Self::SystemData) {
+    let input = &*data.0;
+    let diagonal = data.1.diagonal_movement;
+
+    let digital = if diagonal {
+      diagonal_direction(input)
+    } else {
+      // キーを離しても、まだ押されている中で最後に押された方向に歩き続ける
+      input.most_recent(&MOVEMENT_ACTIONS).map(action_direction)
+    };
+
+    // 方向キーが押されていなければ、スティックの傾きに合わせた速さで歩く
+    // 速さや加速の具合はMovementの設定に任せ、ここでは進みたい向きだけを決める
+    let desired = match digital {
+      Some(direction) => direction.unit_vector(),
+      None => input.analog_move().map_or(Vec2::ZERO, |(x, y)| {
+        let stick = Vec2::new(x, y);
+        let magnitude = stick.length().min(1.0);
+        // 斜めに歩けるならスティックの向きのまま、歩けないなら上下左右のどれかに揃える
+        let direction = if diagonal {
+          stick.normalized()
+        } else {
+          Direction::from_vector(stick, false).map_or(Vec2::ZERO, Direction::unit_vector)
+        };
+        direction * magnitude
+      }),
+    };
+
+    for (_, movement) in (&data.2, &mut data.3).join() {
+      movement.desired = desired;
+    }
+  }
+}
+
+fn action_direction(action: Action) -> Direction {
+  match action {
+    Action::MoveUp => Direction::Up,
+    Action::MoveDown => Direction::Down,
+    Action::MoveLeft => Direction::Left,
+    _ => Direction::Right,
+  }
+}
+
+// 上下と左右それぞれで最後に押された方向を組み合わせる
+fn diagonal_direction(input: &InputState) -> Option<Direction> {
+  use self::Direction::*;
+
+  let vertical = input.most_recent(&[Action::MoveUp, Action::MoveDown]);
+  let horizontal = input.most_recent(&[Action::MoveLeft, Action::MoveRight]);
+  match (vertical, horizontal) {
+    (Some(Action::MoveUp), Some(Action::MoveLeft)) => Some(UpLeft),
+    (Some(Action::MoveUp), Some(_)) => Some(UpRight),
+    (Some(Action::MoveDown), Some(Action::MoveLeft)) => Some(DownLeft),
+    (Some(_), Some(_)) => Some(DownRight),
+    (Some(action), None) | (None, Some(action)) => Some(action_direction(action)),
+    (None, None) => None,
+  }
+}
@@ -1,38 +1,204 @@
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::{Texture, WindowCanvas};
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture};
 use specs::prelude::*;
 
+use crate::assets::{TextureHandle, Textures};
+use crate::camera::Camera;
 use crate::components::*;
+use crate::text::Fonts;
+use crate::tilemap::TileMap;
+use crate::vector::Vec2;
 
-pub type SystemData<'a> = (ReadStorage<'a, Position>, ReadStorage<'a, Sprite>);
+pub type SystemData<'a> = (
+  Entities<'a>,
+  ReadStorage<'a, Position>,
+  ReadStorage<'a, PreviousPosition>,
+  ReadStorage<'a, Sprite>,
+  ReadExpect<'a, TileMap>,
+  ReadExpect<'a, Camera>,
+  ReadStorage<'a, Depth>,
+  ReadStorage<'a, SpriteStyle>,
+  ReadStorage<'a, Text>,
+);
 
-pub fn render(
-  canvas: &mut WindowCanvas,
+// 1枚のスプライトを描く準備ができたもの
+struct Drawable<'a> {
+  layer: RenderLayer,
+  // 同じ層の中で並べる値
+  sort_y: i32,
+  entity: Entity,
+  rect: Rect,
+  sprite: &'a Sprite,
+  style: Option<&'a SpriteStyle>,
+}
+
+// 1フレーム分をcanvasに描く。presentはしないので、呼び出し側で画面に出す
+// alphaは前のステップから今のステップまでの補間係数(0.0..1.0)
+pub fn render<T: RenderTarget, C>(
+  canvas: &mut Canvas<T>,
   background: Color,
-  textures: &[Texture],
+  textures: &mut Textures<C>,
+  fonts: &mut Fonts,
+  alpha: f64,
   data: SystemData,
 ) -> Result<(), String> {
   canvas.set_draw_color(background);
   canvas.clear();
 
-  let (width, height) = canvas.output_size().unwrap();
+  let camera = &*data.5;
+  let view = camera.view(alpha);
+
+  render_tiles(canvas, textures, camera, alpha, &data.4)?;
 
-  for (pos, sprite) in (&data.0, &data.1).join() {
-    let current_frame = sprite.region;
-    let screen_position = pos.0 + Point::new(width as i32 / 2, height as i32 / 2);
-    // screen_positionとspriteの幅と高さを渡すだけで、spriteの中心を計算して指定した座標に描画してくれる
-    let screen_rect = Rect::from_center(
-      screen_position,
-      current_frame.width(),
-      current_frame.height(),
+  let mut drawables = Vec::new();
+  for (entity, pos, prev, sprite, depth, style) in (
+    &data.0,
+    &data.1,
+    data.2.maybe(),
+    &data.3,
+    data.6.maybe(),
+    data.7.maybe(),
+  )
+    .join()
+  {
+    let world_position = match prev {
+      Some(prev) => interpolate(prev.0, pos.0, alpha),
+      None => pos.0.to_point(),
+    };
+    // world_positionとspriteの幅と高さを渡すだけで、spriteの中心を計算して指定した座標の矩形にしてくれる
+    let rect = Rect::from_center(
+      world_position,
+      sprite.region.width(),
+      sprite.region.height(),
     );
-    canvas
-      .copy(&textures[sprite.spritesheet], current_frame, screen_rect)
-      .unwrap();
+    let depth = depth.copied().unwrap_or_default();
+    if depth.layer != RenderLayer::Ui && !view.has_intersection(rect) {
+      continue;
+    }
+    drawables.push(Drawable {
+      layer: depth.layer,
+      sort_y: rect.bottom() + depth.offset,
+      entity,
+      rect,
+      sprite,
+      style,
+    });
+  }
+
+  // 背景、エンティティ、前景、UIの順に描き、同じ層の中では奥(画面の上)にあるものから描く
+  // 並びが同じときはエンティティの番号で決めて、フレームごとに前後が入れ替わらないようにする
+  drawables.sort_by_key(|drawable| (drawable.layer, drawable.sort_y, drawable.entity.id()));
+
+  for drawable in &drawables {
+    let screen_rect = match drawable.layer {
+      RenderLayer::Ui => drawable.rect,
+      _ => camera.world_rect_to_screen(drawable.rect, alpha),
+    };
+    render_sprite(canvas, textures, drawable, screen_rect)?;
   }
 
-  canvas.present();
+  // 文字はUIとして、スプライトよりも手前に描く
+  for (pos, text) in (&data.1, &data.8).join() {
+    fonts.draw(
+      canvas,
+      textures,
+      &text.content,
+      pos.0.to_point(),
+      text.color,
+      text.size,
+    )?;
+  }
 
   Ok(())
 }
+
+fn render_sprite<T: RenderTarget, C>(
+  canvas: &mut Canvas<T>,
+  textures: &mut Textures<C>,
+  drawable: &Drawable,
+  screen_rect: Rect,
+) -> Result<(), String> {
+  let sprite = drawable.sprite;
+  let texture = textures
+    .get_mut(sprite.spritesheet)
+    .ok_or_else(|| format!("texture {:?} is not loaded", sprite.spritesheet))?;
+  let style = match drawable.style {
+    Some(style) => style,
+    None => {
+      return canvas.copy_ex(
+        texture,
+        sprite.region,
+        screen_rect,
+        0.0,
+        None,
+        sprite.flip.horizontal,
+        sprite.flip.vertical,
+      );
+    }
+  };
+
+  // 色と不透明度と合成の仕方はテクスチャに設定されるので、同じスプライトシートを使う他のスプライトのために描いたあと元に戻す
+  // ピボットはスプライトの座標で書かれているので、画面に描く大きさに合わせて伸ばす
+  let pivot = style.pivot.map(|pivot| {
+    Point::new(
+      pivot.x() * screen_rect.width() as i32 / sprite.region.width() as i32,
+      pivot.y() * screen_rect.height() as i32 / sprite.region.height() as i32,
+    )
+  });
+  let (r, g, b) = style.tint.rgb();
+  texture.set_color_mod(r, g, b);
+  texture.set_alpha_mod(style.alpha);
+  let blend_mode = texture.blend_mode();
+  texture.set_blend_mode(BlendMode::Blend);
+  let result = canvas.copy_ex(
+    texture,
+    sprite.region,
+    screen_rect,
+    style.angle,
+    pivot,
+    sprite.flip.horizontal,
+    sprite.flip.vertical,
+  );
+  texture.set_color_mod(255, 255, 255);
+  texture.set_alpha_mod(255);
+  texture.set_blend_mode(blend_mode);
+  result
+}
+
+// 画面に映る範囲のタイルだけを描画する
+fn render_tiles<T: RenderTarget, C>(
+  canvas: &mut Canvas<T>,
+  textures: &Textures<C>,
+  camera: &Camera,
+  alpha: f64,
+  map: &TileMap,
+) -> Result<(), String> {
+  if map.width == 0 || map.height == 0 {
+    return Ok(());
+  }
+  let texture = texture(textures, map.tileset.spritesheet)?;
+
+  for (x, y) in map.tiles_in(camera.view(alpha)) {
+    if let Some(info) = map.tile_info(x, y) {
+      let screen_rect = camera.world_rect_to_screen(map.tile_rect(x, y), alpha);
+      canvas.copy(texture, info.region, screen_rect)?;
+    }
+  }
+
+  Ok(())
+}
+
+fn texture<'a, 'l, C>(
+  textures: &'a Textures<'l, C>,
+  handle: TextureHandle,
+) -> Result<&'a Texture<'l>, String> {
+  textures
+    .get(handle)
+    .ok_or_else(|| format!("texture {:?} is not loaded", handle))
+}
+
+// 補間した位置を、描画するピクセルに丸める
+fn interpolate(from: Vec2, to: Vec2, alpha: f64) -> Point {
+  from.lerp(to, alpha).to_point()
+}
@@ -0,0 +1,272 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, RenderTarget};
+use specs::prelude::*;
+
+use crate::assets::Textures;
+use crate::game::Game;
+use crate::input::{Action, InputState};
+use crate::renderer;
+use crate::text::Fonts;
+
+// ポーズメニューの項目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseItem {
+  Resume,
+  // ゲームをやめてタイトルに戻る
+  Title,
+}
+
+impl PauseItem {
+  pub const ALL: [PauseItem; 2] = [PauseItem::Resume, PauseItem::Title];
+
+  pub fn label(self) -> &'static str {
+    match self {
+      PauseItem::Resume => "Resume",
+      PauseItem::Title => "Quit to title",
+    }
+  }
+}
+
+const WHITE: Color = Color::RGB(255, 255, 255);
+const BLACK: Color = Color::RGB(0, 0, 0);
+
+// 画面の状態。SceneStackに積んで、一番上のシーンだけが入力を受け取る
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+  Title,
+  // ワールドのシステムを動かしているのはこのシーンだけ
+  Playing,
+  // Playingの上に積む。ワールドは止まったまま、下に透けて見える
+  Paused { selected: usize },
+  // プレイヤーが倒れたらPlayingの上に積む
+  GameOver,
+}
+
+// シーンのupdateが返す、次にどうするか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+  Stay,
+  // 今のシーンの上に積む
+  Push(Scene),
+  // 今のシーンを取り除いて、下のシーンに戻る。最後のシーンを取り除くとゲームを終える
+  Pop,
+  // 今のシーンを入れ替える
+  Switch(Scene),
+  // 積んであるシーンを全部取り除いてから積む
+  Reset(Scene),
+}
+
+impl Scene {
+  // 下のシーンの上に重ねて描くかどうか
+  pub fn is_overlay(self) -> bool {
+    match self {
+      Scene::Title | Scene::Playing => false,
+      Scene::Paused { .. } | Scene::GameOver => true,
+    }
+  }
+
+  // シミュレーションを1ステップ進める
+  fn update(&mut self, game: &mut Game) -> Transition {
+    let input = game.world.read_resource::<InputState>();
+    match self {
+      Scene::Title => {
+        if input.just_pressed(Action::Attack) {
+          Transition::Switch(Scene::Playing)
+        } else if input.just_pressed(Action::Pause) {
+          Transition::Pop
+        } else {
+          Transition::Stay
+        }
+      }
+      Scene::Playing => {
+        if input.just_pressed(Action::Pause) {
+          return Transition::Push(Scene::Paused { selected: 0 });
+        }
+        drop(input);
+        game.tick();
+        if game.player_defeated() {
+          Transition::Push(Scene::GameOver)
+        } else {
+          Transition::Stay
+        }
+      }
+      Scene::Paused { selected } => {
+        let count = PauseItem::ALL.len();
+        if input.just_pressed(Action::MoveUp) {
+          *selected = (*selected + count - 1) % count;
+        }
+        if input.just_pressed(Action::MoveDown) {
+          *selected = (*selected + 1) % count;
+        }
+
+        if input.just_pressed(Action::Pause) {
+          Transition::Pop
+        } else if input.just_pressed(Action::Attack) {
+          match PauseItem::ALL[*selected] {
+            PauseItem::Resume => Transition::Pop,
+            PauseItem::Title => Transition::Reset(Scene::Title),
+          }
+        } else {
+          Transition::Stay
+        }
+      }
+      Scene::GameOver => {
+        if input.just_pressed(Action::Attack) || input.just_pressed(Action::Pause) {
+          Transition::Reset(Scene::Title)
+        } else {
+          Transition::Stay
+        }
+      }
+    }
+  }
+
+  fn render<T: RenderTarget, C>(
+    self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    fonts: &mut Fonts,
+    alpha: f64,
+    world: &World,
+    background: Color,
+  ) -> Result<(), String> {
+    let (width, height) = canvas.viewport().size();
+    let center = Point::new(width as i32 / 2, height as i32 / 2);
+    match self {
+      Scene::Title => {
+        canvas.set_draw_color(BLACK);
+        canvas.clear();
+        fonts.draw_centered(canvas, textures, "CAVE", center.offset(0, -48), WHITE, 48)?;
+        fonts.draw_centered(
+          canvas,
+          textures,
+          "Press attack to start",
+          center.offset(0, 48),
+          WHITE,
+          16,
+        )
+      }
+      Scene::Playing => renderer::render(
+        canvas,
+        background,
+        textures,
+        fonts,
+        alpha,
+        world.system_data(),
+      ),
+      Scene::Paused { selected } => {
+        fill_screen(canvas, Color::RGBA(0, 0, 0, 160))?;
+        fonts.draw_centered(canvas, textures, "PAUSED", center.offset(0, -64), WHITE, 32)?;
+        // 項目を縦に並べ、選ばれている項目は白く塗りつぶして黒い文字で描く
+        for (i, item) in PauseItem::ALL.iter().enumerate() {
+          let item_center = center.offset(0, i as i32 * 48);
+          let frame = Rect::from_center(item_center, 240, 32);
+          canvas.set_draw_color(WHITE);
+          let color = if i == selected {
+            canvas.fill_rect(frame)?;
+            BLACK
+          } else {
+            canvas.draw_rect(frame)?;
+            WHITE
+          };
+          fonts.draw_centered(canvas, textures, item.label(), item_center, color, 16)?;
+        }
+        Ok(())
+      }
+      Scene::GameOver => {
+        fill_screen(canvas, Color::RGBA(128, 0, 0, 160))?;
+        fonts.draw_centered(canvas, textures, "GAME OVER", center, WHITE, 48)
+      }
+    }
+  }
+}
+
+// 半透明の色で画面全体を覆う
+fn fill_screen<T: RenderTarget>(canvas: &mut Canvas<T>, color: Color) -> Result<(), String> {
+  canvas.set_blend_mode(BlendMode::Blend);
+  canvas.set_draw_color(color);
+  let result = canvas.fill_rect(None);
+  canvas.set_blend_mode(BlendMode::None);
+  result
+}
+
+// 積み重なったシーン
+#[derive(Debug)]
+pub struct SceneStack {
+  scenes: Vec<Scene>,
+}
+
+impl SceneStack {
+  pub fn new(scene: Scene) -> Self {
+    Self {
+      scenes: vec![scene],
+    }
+  }
+
+  pub fn current(&self) -> Option<Scene> {
+    self.scenes.last().copied()
+  }
+
+  // シーンがなくなったら、ゲームを終える
+  pub fn is_empty(&self) -> bool {
+    self.scenes.is_empty()
+  }
+
+  // 一番上のシーンを1ステップ進めて、返した遷移をあてはめる
+  // 新しいゲームを始めるなど、呼び出し側でも遷移に応じて準備ができるように遷移を返す
+  pub fn update(&mut self, game: &mut Game) -> Transition {
+    let transition = match self.scenes.last_mut() {
+      Some(scene) => scene.update(game),
+      None => Transition::Stay,
+    };
+    // Playing以外はgame.tickを呼ばないので、押された/離されたの記録はここで消す
+    game.world.write_resource::<InputState>().end_tick();
+    // 上に重ねたシーンの間はワールドが止まるので、下に透けて見えるワールドを止まった位置で描く
+    if let Transition::Push(scene) = transition {
+      if scene.is_overlay() {
+        game.settle();
+      }
+    }
+    self.apply(transition);
+    transition
+  }
+
+  pub fn apply(&mut self, transition: Transition) {
+    match transition {
+      Transition::Stay => {}
+      Transition::Push(scene) => self.scenes.push(scene),
+      Transition::Pop => {
+        self.scenes.pop();
+      }
+      Transition::Switch(scene) => {
+        self.scenes.pop();
+        self.scenes.push(scene);
+      }
+      Transition::Reset(scene) => {
+        self.scenes.clear();
+        self.scenes.push(scene);
+      }
+    }
+  }
+
+  // 下のシーンが透けて見えないところから、一番上のシーンまでを順に描く
+  pub fn render<T: RenderTarget, C>(
+    &self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    fonts: &mut Fonts,
+    alpha: f64,
+    world: &World,
+    background: Color,
+  ) -> Result<(), String> {
+    let first = self
+      .scenes
+      .iter()
+      .rposition(|scene| !scene.is_overlay())
+      .unwrap_or(0);
+    for &scene in &self.scenes[first..] {
+      scene.render(canvas, textures, fonts, alpha, world, background)?;
+    }
+    Ok(())
+  }
+}
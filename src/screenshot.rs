@@ -0,0 +1,64 @@
+use sdl2::image::SaveSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Canvas, RenderTarget};
+use sdl2::surface::Surface;
+use specs::prelude::*;
+use std::fs;
+use std::path::Path;
+
+use crate::assets::{AssetManager, Textures};
+use crate::camera::Camera;
+use crate::renderer;
+use crate::text::Fonts;
+
+// canvasに今描かれているものをPNGとして保存する
+// テクスチャに描いている途中(with_texture_canvasの中)なら、そのテクスチャの中身になる
+pub fn save_png<T: RenderTarget, P: AsRef<Path>>(
+  canvas: &Canvas<T>,
+  path: P,
+) -> Result<(), String> {
+  let path = path.as_ref();
+  let size = canvas.viewport().size();
+  let mut pixels = canvas.read_pixels(None, PixelFormatEnum::RGBA32)?;
+  let surface = Surface::from_data(
+    &mut pixels,
+    size.0,
+    size.1,
+    size.0 * 4,
+    PixelFormatEnum::RGBA32,
+  )?;
+
+  if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+  }
+  surface
+    .save(path)
+    .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// ウィンドウを作らずにworldの今の状態を1フレーム描き、PNGとして保存する
+// ソフトウェアレンダラーで描くので、SDL_VIDEODRIVER=dummyの環境(CIなど)でも動く
+// 画面の大きさと映す範囲はworldのCameraに従う
+pub fn capture<P: AsRef<Path>>(
+  world: &World,
+  fonts: &mut Fonts,
+  background: Color,
+  path: P,
+) -> Result<(), String> {
+  let (width, height) = world.read_resource::<Camera>().viewport;
+  let surface = Surface::new(width, height, PixelFormatEnum::RGBA32)?;
+  let mut canvas = surface.into_canvas()?;
+  let texture_creator = canvas.texture_creator();
+  let mut textures = Textures::new(&texture_creator);
+  textures.sync(&world.read_resource::<AssetManager>())?;
+
+  renderer::render(
+    &mut canvas,
+    background,
+    &mut textures,
+    fonts,
+    1.0,
+    world.system_data(),
+  )?;
+  save_png(&canvas, path)
+}
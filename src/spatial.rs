@@ -0,0 +1,274 @@
+use sdl2::rect::Rect;
+use specs::prelude::*;
+use std::collections::HashMap;
+
+use crate::vector::Vec2;
+
+// 何も指定しなかったときのセルの大きさ(ピクセル)。タイルの倍くらいにしておく
+const DEFAULT_CELL_SIZE: u32 = 64;
+
+// raycastが当たった相手
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+  pub entity: Entity,
+  // 始点から当たった所までの距離
+  pub distance: f64,
+  pub point: Vec2,
+}
+
+// 当たり判定を持つエンティティを、ワールドを同じ大きさのセルに区切って入れておくリソース
+// 「この辺りに誰がいるか」を全員と比べずに調べられる。Physicsが毎ステップ作り直し、動いた分を更新する
+#[derive(Debug)]
+pub struct SpatialHash {
+  cell_size: i32,
+  cells: HashMap<(i32, i32), Vec<usize>>,
+  entries: Vec<(Entity, Rect)>,
+  // エンティティごとのentriesの位置
+  lookup: HashMap<Entity, usize>,
+}
+
+impl Default for SpatialHash {
+  fn default() -> Self {
+    Self::new(DEFAULT_CELL_SIZE)
+  }
+}
+
+impl SpatialHash {
+  pub fn new(cell_size: u32) -> Self {
+    Self {
+      cell_size: cell_size.max(1) as i32,
+      cells: HashMap::new(),
+      entries: Vec::new(),
+      lookup: HashMap::new(),
+    }
+  }
+
+  pub fn clear(&mut self) {
+    // セルのVecは次に使うときのために残しておく
+    for cell in self.cells.values_mut() {
+      cell.clear();
+    }
+    self.entries.clear();
+    self.lookup.clear();
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn insert(&mut self, entity: Entity, rect: Rect) {
+    let index = self.entries.len();
+    self.entries.push((entity, rect));
+    self.lookup.insert(entity, index);
+    for cell in self.cells_in(rect) {
+      self.cells.entry(cell).or_default().push(index);
+    }
+  }
+
+  // entityの当たり判定を動かす。入っていなければ何もしない
+  pub fn update(&mut self, entity: Entity, rect: Rect) {
+    let index = match self.lookup.get(&entity) {
+      Some(&index) => index,
+      None => return,
+    };
+    let previous = self.entries[index].1;
+    self.entries[index].1 = rect;
+    if self.cells_in(previous).eq(self.cells_in(rect)) {
+      return;
+    }
+    for cell in self.cells_in(previous) {
+      if let Some(indices) = self.cells.get_mut(&cell) {
+        indices.retain(|&other| other != index);
+      }
+    }
+    for cell in self.cells_in(rect) {
+      self.cells.entry(cell).or_default().push(index);
+    }
+  }
+
+  // 入っているentityの当たり判定
+  pub fn rect(&self, entity: Entity) -> Option<Rect> {
+    self.lookup.get(&entity).map(|&index| self.entries[index].1)
+  }
+
+  // rectと重なる当たり判定を持つエンティティ
+  pub fn query_rect(&self, rect: Rect) -> Vec<Entity> {
+    self
+      .candidates(rect)
+      .into_iter()
+      .map(|index| self.entries[index])
+      .filter(|&(_, other)| overlaps(rect, other))
+      .map(|(entity, _)| entity)
+      .collect()
+  }
+
+  // centerを中心とする半径radiusの円と重なる当たり判定を持つエンティティ
+  pub fn query_radius(&self, center: Vec2, radius: f64) -> Vec<Entity> {
+    let bounds = Rect::new(
+      (center.x - radius).floor() as i32,
+      (center.y - radius).floor() as i32,
+      (radius * 2.0).ceil() as u32 + 1,
+      (radius * 2.0).ceil() as u32 + 1,
+    );
+    self
+      .candidates(bounds)
+      .into_iter()
+      .map(|index| self.entries[index])
+      .filter(|&(_, rect)| {
+        // 矩形の中で円の中心に一番近い点までの距離で比べる
+        let nearest = Vec2::new(
+          center
+            .x
+            .max(f64::from(rect.left()))
+            .min(f64::from(rect.right())),
+          center
+            .y
+            .max(f64::from(rect.top()))
+            .min(f64::from(rect.bottom())),
+        );
+        center.distance(nearest) <= radius
+      })
+      .map(|(entity, _)| entity)
+      .collect()
+  }
+
+  // originからdirectionの向きにmax_distanceまで進む線分と最初に当たる当たり判定
+  // filterがfalseを返したエンティティ(撃った本人など)は通り抜ける
+  pub fn raycast<F: FnMut(Entity) -> bool>(
+    &self,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f64,
+    mut filter: F,
+  ) -> Option<RayHit> {
+    let direction = direction.normalized();
+    if direction == Vec2::ZERO {
+      return None;
+    }
+
+    // 線分が通るセルを始点から順にたどる(DDA)
+    let size = f64::from(self.cell_size);
+    let mut cell = (
+      (origin.x / size).floor() as i32,
+      (origin.y / size).floor() as i32,
+    );
+    let step = |d: f64| if d > 0.0 { 1 } else { -1 };
+    let (step_x, step_y) = (step(direction.x), step(direction.y));
+    // 次のセルの境界までの距離と、セル1つ分を横切るのにかかる距離
+    let boundary = |cell: i32, step: i32, origin: f64, d: f64| {
+      if d == 0.0 {
+        f64::INFINITY
+      } else {
+        let edge = f64::from(if step > 0 { cell + 1 } else { cell }) * size;
+        (edge - origin) / d
+      }
+    };
+    let mut next_x = boundary(cell.0, step_x, origin.x, direction.x);
+    let mut next_y = boundary(cell.1, step_y, origin.y, direction.y);
+    let delta_x = (size / direction.x).abs();
+    let delta_y = (size / direction.y).abs();
+
+    let mut best: Option<(usize, f64)> = None;
+    let mut entered = 0.0;
+    while entered <= max_distance {
+      for &index in self.cells.get(&cell).into_iter().flatten() {
+        let (entity, rect) = self.entries[index];
+        if let Some(distance) = ray_rect(origin, direction, rect) {
+          let closer = best.is_none_or(|(_, best)| distance < best);
+          if distance <= max_distance && closer && filter(entity) {
+            best = Some((index, distance));
+          }
+        }
+      }
+
+      // このセルを出るまでに当たっていれば、それより近い相手は後のセルにはいない
+      let exit = next_x.min(next_y);
+      if let Some((index, distance)) = best {
+        if distance <= exit {
+          return Some(RayHit {
+            entity: self.entries[index].0,
+            distance,
+            point: origin + direction * distance,
+          });
+        }
+      }
+
+      entered = exit;
+      if next_x < next_y {
+        cell.0 += step_x;
+        next_x += delta_x;
+      } else {
+        cell.1 += step_y;
+        next_y += delta_y;
+      }
+    }
+
+    best.map(|(index, distance)| RayHit {
+      entity: self.entries[index].0,
+      distance,
+      point: origin + direction * distance,
+    })
+  }
+
+  fn cells_in(&self, rect: Rect) -> impl Iterator<Item = (i32, i32)> {
+    let size = self.cell_size;
+    let (left, top) = (rect.left().div_euclid(size), rect.top().div_euclid(size));
+    let right = (rect.right() - 1).div_euclid(size);
+    let bottom = (rect.bottom() - 1).div_euclid(size);
+    (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+  }
+
+  // rectが通るセルに入っているエントリ。重複は取り除く
+  fn candidates(&self, rect: Rect) -> Vec<usize> {
+    let mut candidates: Vec<usize> = self
+      .cells_in(rect)
+      .filter_map(|cell| self.cells.get(&cell))
+      .flatten()
+      .copied()
+      .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+  }
+}
+
+// SDLを呼ばずに矩形が重なっているかを調べる。辺が接しているだけなら重なっていない
+fn overlaps(a: Rect, b: Rect) -> bool {
+  a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
+}
+
+// 半直線が矩形に入る所までの距離。始点が矩形の中なら0
+// 角や辺をかすめるだけなら当たっていない
+fn ray_rect(origin: Vec2, direction: Vec2, rect: Rect) -> Option<f64> {
+  let inside = |v: f64, min: i32, max: i32| f64::from(min) <= v && v < f64::from(max);
+  if inside(origin.x, rect.left(), rect.right()) && inside(origin.y, rect.top(), rect.bottom()) {
+    return Some(0.0);
+  }
+  let mut near = 0.0_f64;
+  let mut far = f64::INFINITY;
+  let axes = [
+    (origin.x, direction.x, rect.left(), rect.right()),
+    (origin.y, direction.y, rect.top(), rect.bottom()),
+  ];
+  for &(origin, direction, min, max) in &axes {
+    let (min, max) = (f64::from(min), f64::from(max));
+    if direction == 0.0 {
+      if origin < min || origin >= max {
+        return None;
+      }
+      continue;
+    }
+    let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+    near = near.max(a.min(b));
+    far = far.min(a.max(b));
+  }
+  if near < far {
+    Some(near)
+  } else {
+    None
+  }
+}
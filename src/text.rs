@@ -0,0 +1,294 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Canvas, RenderTarget};
+use serde::Deserialize;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+
+#[cfg(feature = "ttf")]
+use sdl2::ttf::{Font, Sdl2TtfContext};
+#[cfg(feature = "ttf")]
+use std::collections::HashMap;
+#[cfg(feature = "ttf")]
+use std::path::PathBuf;
+
+use crate::assets::{AssetManager, TextureHandle, Textures};
+
+// ビットマップフォントの定義ファイル(RON)の中身。例はassets/fonts/font5x7.ronを参照
+#[derive(Debug, Deserialize)]
+struct BitmapFontDef {
+  spritesheet: String,
+  // 1文字の(幅, 高さ)
+  glyph_size: (u32, u32),
+  // スプライトシートの1行に並んでいる文字の数
+  columns: u32,
+  // スプライトシートの左上にある文字。そこから文字コード順に並べる
+  first_char: char,
+  glyph_count: u32,
+}
+
+// スプライトシートに等幅で並べた文字で描くフォント
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+  spritesheet: TextureHandle,
+  glyph_width: u32,
+  glyph_height: u32,
+  columns: u32,
+  first_char: u32,
+  glyph_count: u32,
+}
+
+impl BitmapFont {
+  pub fn parse(source: &str, assets: &mut AssetManager) -> Result<Self, String> {
+    let def: BitmapFontDef = ron::de::from_str(source).map_err(|e| e.to_string())?;
+    if def.glyph_size.0 == 0 || def.glyph_size.1 == 0 || def.columns == 0 {
+      return Err("glyph_size and columns must not be zero".to_string());
+    }
+    Ok(Self {
+      spritesheet: assets.load(&def.spritesheet)?,
+      glyph_width: def.glyph_size.0,
+      glyph_height: def.glyph_size.1,
+      columns: def.columns,
+      first_char: u32::from(def.first_char),
+      glyph_count: def.glyph_count,
+    })
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P, assets: &mut AssetManager) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Self::parse(&source, assets).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  // 1行の高さがsizeにいちばん近くなる整数倍で拡大する。ドットがにじまないように小数倍にはしない
+  fn scale(&self, size: u32) -> u32 {
+    ((size + self.glyph_height / 2) / self.glyph_height).max(1)
+  }
+
+  // スプライトシートの中のcの領域。フォントにない文字ならNone
+  fn glyph(&self, c: char) -> Option<Rect> {
+    let index = u32::from(c).checked_sub(self.first_char)?;
+    if index >= self.glyph_count {
+      return None;
+    }
+    Some(Rect::new(
+      ((index % self.columns) * self.glyph_width) as i32,
+      ((index / self.columns) * self.glyph_height) as i32,
+      self.glyph_width,
+      self.glyph_height,
+    ))
+  }
+
+  fn measure(&self, text: &str, size: u32) -> (u32, u32) {
+    let scale = self.scale(size);
+    let columns = text
+      .lines()
+      .map(|line| line.chars().count())
+      .max()
+      .unwrap_or(0);
+    let lines = text.lines().count();
+    (
+      columns as u32 * self.glyph_width * scale,
+      lines as u32 * self.glyph_height * scale,
+    )
+  }
+
+  fn draw<T: RenderTarget, C>(
+    &self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    text: &str,
+    position: Point,
+    color: Color,
+    size: u32,
+  ) -> Result<(), String> {
+    let scale = self.scale(size);
+    let (width, height) = (self.glyph_width * scale, self.glyph_height * scale);
+    let texture = textures
+      .get_mut(self.spritesheet)
+      .ok_or_else(|| format!("texture {:?} is not loaded", self.spritesheet))?;
+
+    // 文字は白で描いておき、色はテクスチャのカラーモジュレーションでつける
+    texture.set_color_mod(color.r, color.g, color.b);
+    texture.set_alpha_mod(color.a);
+    let mut result = Ok(());
+    for (row, line) in text.lines().enumerate() {
+      for (column, c) in line.chars().enumerate() {
+        // フォントにない文字は空白にする
+        if let Some(glyph) = self.glyph(c) {
+          let dst = Rect::new(
+            position.x() + (column as u32 * width) as i32,
+            position.y() + (row as u32 * height) as i32,
+            width,
+            height,
+          );
+          result = result.and(canvas.copy(texture, glyph, dst));
+        }
+      }
+    }
+    texture.set_color_mod(255, 255, 255);
+    texture.set_alpha_mod(255);
+    result
+  }
+}
+
+// SDL2_ttfで読み込んだTrueTypeフォント。大きさごとに読み込んで持っておく
+#[cfg(feature = "ttf")]
+pub struct TtfFont<'ttf> {
+  context: &'ttf Sdl2TtfContext,
+  path: PathBuf,
+  sizes: HashMap<u32, Font<'ttf, 'static>>,
+}
+
+#[cfg(feature = "ttf")]
+impl<'ttf> TtfFont<'ttf> {
+  // 読み込めるかどうかを確かめるために、1つの大きさで読み込んでおく
+  pub fn load<P: AsRef<Path>>(context: &'ttf Sdl2TtfContext, path: P) -> Result<Self, String> {
+    let mut font = Self {
+      context,
+      path: path.as_ref().to_path_buf(),
+      sizes: HashMap::new(),
+    };
+    font.font(16)?;
+    Ok(font)
+  }
+
+  fn font(&mut self, size: u32) -> Result<&Font<'ttf, 'static>, String> {
+    if !self.sizes.contains_key(&size) {
+      let font = self
+        .context
+        .load_font(&self.path, size.min(u32::from(u16::MAX)) as u16)
+        .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+      self.sizes.insert(size, font);
+    }
+    Ok(&self.sizes[&size])
+  }
+
+  fn measure(&mut self, text: &str, size: u32) -> Result<(u32, u32), String> {
+    let font = self.font(size)?;
+    let line_height = font.recommended_line_spacing().max(0) as u32;
+    let mut width = 0;
+    for line in text.lines() {
+      width = width.max(font.size_of(line).map_err(|e| e.to_string())?.0);
+    }
+    Ok((width, text.lines().count() as u32 * line_height))
+  }
+
+  fn draw<T: RenderTarget, C>(
+    &mut self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    text: &str,
+    position: Point,
+    color: Color,
+    size: u32,
+  ) -> Result<(), String> {
+    let line_height = self.font(size)?.recommended_line_spacing();
+    let font = &self.sizes[&size];
+    for (row, line) in text.lines().enumerate() {
+      // 空の行はSDL2_ttfが描けないので飛ばす
+      if line.is_empty() {
+        continue;
+      }
+      // 毎フレーム描き直すと重いので、同じ文字列と色なら前のフレームで作ったテクスチャを使う
+      let key = format!(
+        "ttf {} {} {:?} {}",
+        self.path.display(),
+        size,
+        color.rgba(),
+        line
+      );
+      let texture = textures.generated(&key, |creator| {
+        let surface = font
+          .render(line)
+          .blended(color)
+          .map_err(|e| e.to_string())?;
+        creator
+          .create_texture_from_surface(&surface)
+          .map_err(|e| e.to_string())
+      })?;
+      let query = texture.query();
+      let dst = Rect::new(
+        position.x(),
+        position.y() + row as i32 * line_height,
+        query.width,
+        query.height,
+      );
+      canvas.copy(texture, None, dst)?;
+    }
+    Ok(())
+  }
+}
+
+// 文字列を描くためのフォント
+// TrueTypeフォントがあればそれで描き、なければビットマップフォントで描く
+pub struct Fonts<'ttf> {
+  bitmap: BitmapFont,
+  #[cfg(feature = "ttf")]
+  ttf: Option<TtfFont<'ttf>>,
+  _ttf: PhantomData<&'ttf ()>,
+}
+
+impl<'ttf> Fonts<'ttf> {
+  pub fn new(bitmap: BitmapFont) -> Self {
+    Self {
+      bitmap,
+      #[cfg(feature = "ttf")]
+      ttf: None,
+      _ttf: PhantomData,
+    }
+  }
+
+  #[cfg(feature = "ttf")]
+  pub fn set_ttf(&mut self, font: TtfFont<'ttf>) {
+    self.ttf = Some(font);
+  }
+
+  // 描いたときの(幅, 高さ)。sizeは1行の高さ(ピクセル)の目安
+  pub fn measure(&mut self, text: &str, size: u32) -> Result<(u32, u32), String> {
+    #[cfg(feature = "ttf")]
+    {
+      if let Some(ttf) = &mut self.ttf {
+        return ttf.measure(text, size);
+      }
+    }
+    Ok(self.bitmap.measure(text, size))
+  }
+
+  // positionを左上にして描く。改行で次の行に移る
+  pub fn draw<T: RenderTarget, C>(
+    &mut self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    text: &str,
+    position: Point,
+    color: Color,
+    size: u32,
+  ) -> Result<(), String> {
+    #[cfg(feature = "ttf")]
+    {
+      if let Some(ttf) = &mut self.ttf {
+        return ttf.draw(canvas, textures, text, position, color, size);
+      }
+    }
+    self
+      .bitmap
+      .draw(canvas, textures, text, position, color, size)
+  }
+
+  // positionを中心にして描く
+  pub fn draw_centered<T: RenderTarget, C>(
+    &mut self,
+    canvas: &mut Canvas<T>,
+    textures: &mut Textures<C>,
+    text: &str,
+    position: Point,
+    color: Color,
+    size: u32,
+  ) -> Result<(), String> {
+    let (width, height) = self.measure(text, size)?;
+    let top_left = position.offset(-(width as i32) / 2, -(height as i32) / 2);
+    self.draw(canvas, textures, text, top_left, color, size)
+  }
+}
@@ -0,0 +1,603 @@
+use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
+use specs::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::assets::AssetManager;
+use crate::components::*;
+use crate::tilemap::{TileId, TileMap, Tileset};
+
+// gidの上位ビットはタイルの反転を表すので、タイルを探すときは取り除く
+const GID_FLAGS: u32 = 0xF000_0000;
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+// 斜めの反転(90度の回転)と六角形マップの回転は表せない
+const ROTATED: u32 = 0x3000_0000;
+// マップの幅と高さ(タイル数)の上限。壊れたファイルで巨大な配列を確保しないようにする
+const MAX_MAP_SIZE: i32 = 4096;
+
+// Tiledで作ったマップを読み込んだ結果
+#[derive(Debug, Clone)]
+pub struct TiledMap {
+  pub map: TileMap,
+  pub objects: Vec<TiledObject>,
+}
+
+// オブジェクトレイヤーに置かれたオブジェクト
+#[derive(Debug, Clone)]
+pub struct TiledObject {
+  pub name: String,
+  // Tiledで設定したtype(1.9ではclass)
+  pub kind: String,
+  // ワールド座標での領域
+  pub rect: Rect,
+  // タイルオブジェクトなら、そのタイル
+  pub tile: Option<TileId>,
+  // タイルオブジェクトをTiledで反転させていれば、その向き
+  pub flip: Flip,
+  // カスタムプロパティのsolid。trueなら通り抜けられない当たり判定をつける
+  pub solid: bool,
+}
+
+impl TiledMap {
+  // .tmx(XML)と.tmj(JSON)のどちらも読める
+  // タイルセットはマップに埋め込んだものと、同じ形式の外部ファイル(.tsx/.tsj)のどちらでもよい
+  // レイヤーの圧縮には対応していないので、tmxはCSVで保存する
+  // 反転できるのはタイルオブジェクトだけで、回転とグループレイヤーはエラーにする
+  pub fn load<P: AsRef<Path>>(path: P, assets: &mut AssetManager) -> Result<Self, String> {
+    let path = path.as_ref();
+    let raw = match path.extension().and_then(|ext| ext.to_str()) {
+      Some("tmx") => parse_tmx(path),
+      Some("tmj") | Some("json") => parse_tmj(path),
+      _ => Err("expected a .tmx or .tmj file".to_string()),
+    }
+    .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    raw
+      .build(assets)
+      .map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  // typeがplayerのオブジェクトの中心。なければNone
+  pub fn player_spawn(&self) -> Option<Point> {
+    self
+      .objects
+      .iter()
+      .find(|object| object.kind == "player")
+      .map(|object| object.rect.center())
+  }
+
+  // typeがenemyのオブジェクトの中心
+  pub fn enemy_spawns(&self) -> Vec<Point> {
+    self
+      .objects
+      .iter()
+      .filter(|object| object.kind == "enemy")
+      .map(|object| object.rect.center())
+      .collect()
+  }
+
+  // playerとenemy以外のオブジェクトをエンティティにする
+  // タイルオブジェクトはそのタイルのSpriteを持ち、どのオブジェクトも領域と同じ大きさのColliderを持つ
+  pub fn spawn_objects(&self, world: &mut World) -> Vec<Entity> {
+    self
+      .objects
+      .iter()
+      .filter(|object| object.kind != "player" && object.kind != "enemy")
+      .map(|object| {
+        let center = object.rect.center();
+        let mut hitbox = object.rect;
+        hitbox.offset(-center.x(), -center.y());
+
+        let mut builder = world
+          .create_entity()
+          .with(Position(center.into()))
+          .with(Collider {
+            hitbox,
+            solid: object.solid,
+          });
+        if let Some(info) = object.tile.and_then(|id| self.map.tileset.tiles.get(id)) {
+          builder = builder.with(Sprite {
+            spritesheet: self.map.tileset.spritesheet,
+            region: info.region,
+            flip: object.flip,
+          });
+        }
+        builder.build()
+      })
+      .collect()
+  }
+}
+
+// tmxとtmjのどちらからでも作る、形式に依存しないマップの中身
+#[derive(Debug, Default)]
+struct RawMap {
+  width: i32,
+  height: i32,
+  tilesets: Vec<RawTileset>,
+  layers: Vec<Vec<u32>>,
+  objects: Vec<RawObject>,
+}
+
+#[derive(Debug, Default)]
+struct RawTileset {
+  first_gid: u32,
+  // マップ(外部タイルセットならそのファイル)からの相対パスを解決したもの
+  image: PathBuf,
+  tile_width: u32,
+  tile_height: u32,
+  columns: u32,
+  tile_count: u32,
+  solid: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+struct RawObject {
+  name: String,
+  kind: String,
+  x: f64,
+  y: f64,
+  width: f64,
+  height: f64,
+  gid: Option<u32>,
+  // 度数法で時計回り
+  rotation: f64,
+  solid: bool,
+}
+
+impl RawMap {
+  fn build(self, assets: &mut AssetManager) -> Result<TiledMap, String> {
+    let valid = |size: i32| size > 0 && size <= MAX_MAP_SIZE;
+    if !valid(self.width) || !valid(self.height) {
+      return Err(format!(
+        "the map size {}x{} must be between 1 and {} tiles",
+        self.width, self.height, MAX_MAP_SIZE
+      ));
+    }
+    let tile_count = self
+      .width
+      .checked_mul(self.height)
+      .ok_or_else(|| "the map is too large".to_string())? as usize;
+
+    let raw_tileset = match self.tilesets.as_slice() {
+      [tileset] => tileset,
+      [] => return Err("the map has no tileset".to_string()),
+      _ => return Err("only one tileset per map is supported".to_string()),
+    };
+    if raw_tileset.columns == 0 {
+      return Err("the tileset has no columns".to_string());
+    }
+    if raw_tileset.tile_width == 0 || raw_tileset.tile_height == 0 {
+      return Err(format!(
+        "the tile size {}x{} must not be zero",
+        raw_tileset.tile_width, raw_tileset.tile_height
+      ));
+    }
+
+    let spritesheet = assets.load(&raw_tileset.image)?;
+    let solid: Vec<bool> = (0..raw_tileset.tile_count)
+      .map(|id| raw_tileset.solid.contains(&id))
+      .collect();
+    let tileset = Tileset::from_grid(
+      spritesheet,
+      raw_tileset.tile_width,
+      raw_tileset.tile_height,
+      raw_tileset.columns,
+      &solid,
+    );
+
+    let tile_id = |gid: u32| -> Result<Option<TileId>, String> {
+      if gid & ROTATED != 0 {
+        return Err(format!("rotated tile gid {:#x} is not supported", gid));
+      }
+      let gid = gid & !GID_FLAGS;
+      if gid == 0 {
+        return Ok(None);
+      }
+      let id = gid
+        .checked_sub(raw_tileset.first_gid)
+        .filter(|&id| id < raw_tileset.tile_count)
+        .ok_or_else(|| format!("tile gid {} is not in the tileset", gid))?;
+      Ok(Some(id as TileId))
+    };
+
+    // 後のレイヤーのタイルが前のレイヤーのタイルを上書きする
+    let mut map = TileMap::new(self.width, self.height, tileset, None);
+    for layer in &self.layers {
+      if layer.len() != tile_count {
+        return Err("a tile layer does not match the map size".to_string());
+      }
+      for (i, &gid) in layer.iter().enumerate() {
+        // TileMapはマスごとに向きを持たないので、反転したタイルは描けない
+        if gid & GID_FLAGS != 0 {
+          return Err(format!(
+            "flipped tile gid {:#x} in a tile layer is not supported",
+            gid
+          ));
+        }
+        if let Some(id) = tile_id(gid)? {
+          let i = i as i32;
+          map.set(i % self.width, i / self.width, Some(id));
+        }
+      }
+    }
+
+    let objects = self
+      .objects
+      .iter()
+      .map(|object| {
+        if object.rotation != 0.0 {
+          return Err(format!("the object {:?} is rotated", object.name));
+        }
+        let tile = match object.gid {
+          Some(gid) => tile_id(gid)?,
+          None => None,
+        };
+        // タイルオブジェクトの座標は左下を指している
+        let top = if tile.is_some() {
+          object.y - object.height
+        } else {
+          object.y
+        };
+        Ok(TiledObject {
+          name: object.name.clone(),
+          kind: object.kind.clone(),
+          rect: Rect::new(
+            object.x.round() as i32,
+            top.round() as i32,
+            object.width.round().max(1.0) as u32,
+            object.height.round().max(1.0) as u32,
+          ),
+          tile,
+          flip: Flip {
+            horizontal: object.gid.unwrap_or(0) & FLIPPED_HORIZONTALLY != 0,
+            vertical: object.gid.unwrap_or(0) & FLIPPED_VERTICALLY != 0,
+          },
+          solid: object.solid,
+        })
+      })
+      .collect::<Result<_, String>>()?;
+
+    Ok(TiledMap { map, objects })
+  }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+  fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// pathと同じディレクトリにあるファイルとしてrelativeを解決する
+fn sibling(path: &Path, relative: &str) -> PathBuf {
+  path
+    .parent()
+    .unwrap_or_else(|| Path::new(""))
+    .join(relative)
+}
+
+// ---- tmj(JSON) ----
+
+#[derive(Debug, Deserialize)]
+struct JsonMap {
+  width: i32,
+  height: i32,
+  #[serde(default)]
+  infinite: bool,
+  tilesets: Vec<JsonTileset>,
+  layers: Vec<JsonLayer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum JsonLayer {
+  #[serde(rename = "tilelayer")]
+  Tiles {
+    data: Option<Vec<u32>>,
+    encoding: Option<String>,
+  },
+  #[serde(rename = "objectgroup")]
+  Objects { objects: Vec<JsonObject> },
+  #[serde(rename = "group")]
+  Group {},
+  #[serde(other)]
+  Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonTileset {
+  firstgid: u32,
+  source: Option<String>,
+  image: String,
+  tilewidth: u32,
+  tileheight: u32,
+  columns: u32,
+  tilecount: u32,
+  tiles: Vec<JsonTile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonTile {
+  id: u32,
+  properties: Vec<JsonProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonProperty {
+  name: String,
+  value: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonObject {
+  name: String,
+  #[serde(rename = "type")]
+  kind: String,
+  class: String,
+  x: f64,
+  y: f64,
+  width: f64,
+  height: f64,
+  gid: Option<u32>,
+  rotation: f64,
+  properties: Vec<JsonProperty>,
+}
+
+fn json_solid(properties: &[JsonProperty]) -> bool {
+  properties
+    .iter()
+    .any(|property| property.name == "solid" && property.value == serde_json::Value::Bool(true))
+}
+
+fn parse_tmj(path: &Path) -> Result<RawMap, String> {
+  let json: JsonMap = serde_json::from_str(&read(path)?).map_err(|e| e.to_string())?;
+  if json.infinite {
+    return Err("infinite maps are not supported".to_string());
+  }
+
+  let mut raw = RawMap {
+    width: json.width,
+    height: json.height,
+    ..RawMap::default()
+  };
+
+  for tileset in json.tilesets {
+    let first_gid = tileset.firstgid;
+    // 外部タイルセットはfirstgidとsourceだけを持っている
+    let (tileset, base) = match &tileset.source {
+      Some(source) => {
+        let tileset_path = sibling(path, source);
+        let external: JsonTileset =
+          serde_json::from_str(&read(&tileset_path)?).map_err(|e| format!("{}: {}", source, e))?;
+        (external, tileset_path)
+      }
+      None => (tileset, path.to_path_buf()),
+    };
+    raw.tilesets.push(RawTileset {
+      first_gid,
+      image: sibling(&base, &tileset.image),
+      tile_width: tileset.tilewidth,
+      tile_height: tileset.tileheight,
+      columns: tileset.columns,
+      tile_count: tileset.tilecount,
+      solid: tileset
+        .tiles
+        .iter()
+        .filter(|tile| json_solid(&tile.properties))
+        .map(|tile| tile.id)
+        .collect(),
+    });
+  }
+
+  for layer in json.layers {
+    match layer {
+      JsonLayer::Tiles { data, encoding } => {
+        if encoding
+          .as_deref()
+          .is_some_and(|encoding| encoding != "csv")
+        {
+          return Err("encoded tile layers are not supported".to_string());
+        }
+        raw.layers.push(data.unwrap_or_default());
+      }
+      JsonLayer::Objects { objects } => {
+        raw
+          .objects
+          .extend(objects.into_iter().map(|object| RawObject {
+            solid: json_solid(&object.properties),
+            kind: if object.kind.is_empty() {
+              object.class
+            } else {
+              object.kind
+            },
+            name: object.name,
+            x: object.x,
+            y: object.y,
+            width: object.width,
+            height: object.height,
+            gid: object.gid,
+            rotation: object.rotation,
+          }));
+      }
+      JsonLayer::Group {} => return Err("group layers are not supported".to_string()),
+      JsonLayer::Other => {}
+    }
+  }
+
+  Ok(raw)
+}
+
+// ---- tmx(XML) ----
+
+// XMLを木にしたもの。tmxは小さいので全部読んでから辿る
+#[derive(Debug, Default)]
+struct Element {
+  name: String,
+  attributes: HashMap<String, String>,
+  children: Vec<Element>,
+  text: String,
+}
+
+impl Element {
+  fn parse(source: &str) -> Result<Element, String> {
+    let mut stack: Vec<Element> = vec![Element::default()];
+    for event in EventReader::from_str(source) {
+      match event.map_err(|e| e.to_string())? {
+        XmlEvent::StartElement {
+          name, attributes, ..
+        } => stack.push(Element {
+          name: name.local_name,
+          attributes: attributes
+            .into_iter()
+            .map(|attribute| (attribute.name.local_name, attribute.value))
+            .collect(),
+          ..Element::default()
+        }),
+        XmlEvent::EndElement { .. } => {
+          let element = stack.pop().ok_or("unbalanced XML")?;
+          stack
+            .last_mut()
+            .ok_or("unbalanced XML")?
+            .children
+            .push(element);
+        }
+        XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+          if let Some(element) = stack.last_mut() {
+            element.text.push_str(&text);
+          }
+        }
+        _ => {}
+      }
+    }
+
+    let mut document = stack.pop().ok_or("unbalanced XML")?;
+    document
+      .children
+      .pop()
+      .ok_or_else(|| "empty XML".to_string())
+  }
+
+  fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    self.children.iter().filter(move |child| child.name == name)
+  }
+
+  fn child<'a>(&'a self, name: &'a str) -> Option<&'a Element> {
+    self.children(name).next()
+  }
+
+  fn attribute(&self, name: &str) -> Option<&str> {
+    self.attributes.get(name).map(String::as_str)
+  }
+
+  fn parse_attribute<T: std::str::FromStr>(&self, name: &str) -> Result<T, String> {
+    self
+      .attribute(name)
+      .ok_or_else(|| format!("<{}> has no {} attribute", self.name, name))?
+      .parse()
+      .map_err(|_| format!("<{}> has an invalid {} attribute", self.name, name))
+  }
+
+  fn solid(&self) -> bool {
+    self.child("properties").is_some_and(|properties| {
+      properties.children("property").any(|property| {
+        property.attribute("name") == Some("solid") && property.attribute("value") == Some("true")
+      })
+    })
+  }
+}
+
+fn parse_tmx(path: &Path) -> Result<RawMap, String> {
+  let root = Element::parse(&read(path)?)?;
+  if root.attribute("infinite") == Some("1") {
+    return Err("infinite maps are not supported".to_string());
+  }
+
+  let mut raw = RawMap {
+    width: root.parse_attribute("width")?,
+    height: root.parse_attribute("height")?,
+    ..RawMap::default()
+  };
+
+  for tileset in root.children("tileset") {
+    let first_gid = tileset.parse_attribute("firstgid")?;
+    let external;
+    let (tileset, base) = match tileset.attribute("source") {
+      Some(source) => {
+        let tileset_path = sibling(path, source);
+        external =
+          Element::parse(&read(&tileset_path)?).map_err(|e| format!("{}: {}", source, e))?;
+        (&external, tileset_path)
+      }
+      None => (tileset, path.to_path_buf()),
+    };
+
+    let image = tileset
+      .child("image")
+      .and_then(|image| image.attribute("source"))
+      .ok_or("the tileset has no image")?;
+    raw.tilesets.push(RawTileset {
+      first_gid,
+      image: sibling(&base, image),
+      tile_width: tileset.parse_attribute("tilewidth")?,
+      tile_height: tileset.parse_attribute("tileheight")?,
+      columns: tileset.parse_attribute("columns")?,
+      tile_count: tileset.parse_attribute("tilecount")?,
+      solid: tileset
+        .children("tile")
+        .filter(|tile| tile.solid())
+        .map(|tile| tile.parse_attribute("id"))
+        .collect::<Result<_, _>>()?,
+    });
+  }
+
+  // レイヤーとオブジェクトグループは書かれた順に重ねる
+  for child in &root.children {
+    match child.name.as_str() {
+      "layer" => {
+        let data = child.child("data").ok_or("the layer has no data")?;
+        if data.attribute("encoding") != Some("csv") || data.attribute("compression").is_some() {
+          return Err("only CSV tile layers are supported".to_string());
+        }
+        let gids = data
+          .text
+          .split(',')
+          .map(str::trim)
+          .filter(|gid| !gid.is_empty())
+          .map(|gid| {
+            gid
+              .parse::<u32>()
+              .map_err(|_| format!("invalid tile gid {:?}", gid))
+          })
+          .collect::<Result<_, _>>()?;
+        raw.layers.push(gids);
+      }
+      "objectgroup" => {
+        for object in child.children("object") {
+          raw.objects.push(RawObject {
+            name: object.attribute("name").unwrap_or_default().to_string(),
+            kind: object
+              .attribute("type")
+              .or_else(|| object.attribute("class"))
+              .unwrap_or_default()
+              .to_string(),
+            x: object.parse_attribute("x")?,
+            y: object.parse_attribute("y")?,
+            width: object.parse_attribute("width").unwrap_or(0.0),
+            height: object.parse_attribute("height").unwrap_or(0.0),
+            gid: object.parse_attribute("gid").ok(),
+            rotation: object.parse_attribute("rotation").unwrap_or(0.0),
+            solid: object.solid(),
+          });
+        }
+      }
+      "group" => return Err("group layers are not supported".to_string()),
+      _ => {}
+    }
+  }
+
+  Ok(raw)
+}
@@ -0,0 +1,154 @@
+use sdl2::rect::{Point, Rect};
+
+use crate::assets::TextureHandle;
+
+// タイルセットの中の何番目のタイルかを表す番号
+pub type TileId = usize;
+
+#[derive(Debug, Clone)]
+pub struct TileInfo {
+  // スプライトシートの中でこのタイルが描かれている領域
+  pub region: Rect,
+  // trueなら壁のように通り抜けられない
+  pub solid: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tileset {
+  // タイルを切り出すスプライトシート
+  pub spritesheet: TextureHandle,
+  pub tile_width: u32,
+  pub tile_height: u32,
+  pub tiles: Vec<TileInfo>,
+}
+
+impl Tileset {
+  // スプライトシートを左上から横にcolumns個ずつ並んだタイルとして切り出す
+  // solidの長さがタイルの数になる
+  pub fn from_grid(
+    spritesheet: TextureHandle,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    solid: &[bool],
+  ) -> Self {
+    let tiles = solid
+      .iter()
+      .enumerate()
+      .map(|(i, &solid)| {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        TileInfo {
+          region: Rect::new(
+            (column * tile_width) as i32,
+            (row * tile_height) as i32,
+            tile_width,
+            tile_height,
+          ),
+          solid,
+        }
+      })
+      .collect();
+
+    Self {
+      spritesheet,
+      tile_width,
+      tile_height,
+      tiles,
+    }
+  }
+}
+
+// 洞窟の地形を表すリソース。タイル(0, 0)の左上がワールド座標の原点になる
+#[derive(Debug, Clone, Default)]
+pub struct TileMap {
+  pub width: i32,
+  pub height: i32,
+  pub tileset: Tileset,
+  // Noneのマスには何も描かず、通り抜けられる
+  tiles: Vec<Option<TileId>>,
+}
+
+impl TileMap {
+  pub fn new(width: i32, height: i32, tileset: Tileset, fill: Option<TileId>) -> Self {
+    Self {
+      width,
+      height,
+      tileset,
+      tiles: vec![fill; (width * height) as usize],
+    }
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    if x < 0 || y < 0 || x >= self.width || y >= self.height {
+      None
+    } else {
+      Some((y * self.width + x) as usize)
+    }
+  }
+
+  pub fn get(&self, x: i32, y: i32) -> Option<TileId> {
+    self.index(x, y).and_then(|i| self.tiles[i])
+  }
+
+  pub fn set(&mut self, x: i32, y: i32, tile: Option<TileId>) {
+    if let Some(i) = self.index(x, y) {
+      self.tiles[i] = tile;
+    }
+  }
+
+  pub fn tile_info(&self, x: i32, y: i32) -> Option<&TileInfo> {
+    self.get(x, y).and_then(|id| self.tileset.tiles.get(id))
+  }
+
+  // マップの外は壁として扱う。ただしマップが空のときはどこにも壁がない
+  pub fn is_solid(&self, x: i32, y: i32) -> bool {
+    if self.index(x, y).is_none() {
+      return !self.tiles.is_empty();
+    }
+    self.tile_info(x, y).is_some_and(|info| info.solid)
+  }
+
+  pub fn pixel_size(&self) -> (u32, u32) {
+    (
+      self.width as u32 * self.tileset.tile_width,
+      self.height as u32 * self.tileset.tile_height,
+    )
+  }
+
+  // ワールド座標の点がどのタイルの上にあるか
+  // タイルの大きさが0のとき(Tileset::default()など)は、1ピクセルを1マスとみなして0で割らないようにする
+  pub fn tile_at(&self, point: Point) -> (i32, i32) {
+    (
+      point
+        .x()
+        .div_euclid((self.tileset.tile_width as i32).max(1)),
+      point
+        .y()
+        .div_euclid((self.tileset.tile_height as i32).max(1)),
+    )
+  }
+
+  // タイルが占めるワールド座標の領域
+  pub fn tile_rect(&self, x: i32, y: i32) -> Rect {
+    Rect::new(
+      x * self.tileset.tile_width as i32,
+      y * self.tileset.tile_height as i32,
+      self.tileset.tile_width,
+      self.tileset.tile_height,
+    )
+  }
+
+  // ワールド座標の領域に重なっているタイルの座標を列挙する(マップの外も含む)
+  pub fn tiles_in(&self, area: Rect) -> impl Iterator<Item = (i32, i32)> {
+    let ((left, top), (right, bottom)) =
+      if self.tileset.tile_width == 0 || self.tileset.tile_height == 0 {
+        ((0, 0), (-1, -1))
+      } else {
+        (
+          self.tile_at(area.top_left()),
+          self.tile_at(Point::new(area.right() - 1, area.bottom() - 1)),
+        )
+      };
+    (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+  }
+}
@@ -0,0 +1,57 @@
+use std::time::Instant;
+
+// シミュレーションを1回進める時間(秒)
+pub const SIMULATION_STEP: f64 = 1.0 / 60.0;
+
+// 描画が極端に遅れたときに、シミュレーションが追いつこうとして止まらなくなるのを防ぐ
+const MAX_FRAME_TIME: f64 = 0.25;
+
+// 1回のdispatchで進める時間(秒)を表すリソース
+#[derive(Debug, Clone, Copy)]
+pub struct DeltaTime(pub f64);
+
+impl Default for DeltaTime {
+  fn default() -> Self {
+    DeltaTime(SIMULATION_STEP)
+  }
+}
+
+// 実時間を貯めておき、固定の刻み幅でシミュレーションを進めるためのアキュムレータ
+pub struct FixedTimestep {
+  step: f64,
+  accumulator: f64,
+  last_instant: Instant,
+}
+
+impl FixedTimestep {
+  pub fn new(step: f64) -> Self {
+    Self {
+      step,
+      accumulator: 0.0,
+      last_instant: Instant::now(),
+    }
+  }
+
+  // 前回呼び出してから経過した実時間をアキュムレータに足す
+  pub fn advance(&mut self) {
+    let now = Instant::now();
+    let frame_time = now.duration_since(self.last_instant);
+    self.last_instant = now;
+    self.accumulator += frame_time.as_secs_f64().min(MAX_FRAME_TIME);
+  }
+
+  // シミュレーションを1ステップ進められるならtrueを返し、その分をアキュムレータから引く
+  pub fn consume_step(&mut self) -> bool {
+    if self.accumulator >= self.step {
+      self.accumulator -= self.step;
+      true
+    } else {
+      false
+    }
+  }
+
+  // 前のステップと今のステップの間のどこを描画するか(0.0..1.0)
+  pub fn alpha(&self) -> f64 {
+    self.accumulator / self.step
+  }
+}
@@ -0,0 +1,101 @@
+use sdl2::rect::Point;
+use serde::Deserialize;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+// シミュレーションで使う2次元ベクトル。yは下向きが正
+// 描画するときだけPointに丸める
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub struct Vec2 {
+  pub x: f64,
+  pub y: f64,
+}
+
+impl Vec2 {
+  pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+  pub const fn new(x: f64, y: f64) -> Self {
+    Self { x, y }
+  }
+
+  pub fn length(self) -> f64 {
+    self.x.hypot(self.y)
+  }
+
+  // 同じ向きで長さ1のベクトル。長さ0のベクトルはそのまま返す
+  pub fn normalized(self) -> Vec2 {
+    let length = self.length();
+    if length == 0.0 {
+      self
+    } else {
+      self * (1.0 / length)
+    }
+  }
+
+  pub fn dot(self, other: Vec2) -> f64 {
+    self.x * other.x + self.y * other.y
+  }
+
+  pub fn distance(self, other: Vec2) -> f64 {
+    (other - self).length()
+  }
+
+  // tが0ならself、1ならotherになる
+  pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+    self + (other - self) * t
+  }
+
+  // 一番近い整数の座標
+  pub fn to_point(self) -> Point {
+    Point::new(self.x.round() as i32, self.y.round() as i32)
+  }
+}
+
+impl From<Point> for Vec2 {
+  fn from(point: Point) -> Self {
+    Vec2::new(f64::from(point.x()), f64::from(point.y()))
+  }
+}
+
+impl Add for Vec2 {
+  type Output = Vec2;
+
+  fn add(self, other: Vec2) -> Vec2 {
+    Vec2::new(self.x + other.x, self.y + other.y)
+  }
+}
+
+impl AddAssign for Vec2 {
+  fn add_assign(&mut self, other: Vec2) {
+    *self = *self + other;
+  }
+}
+
+impl Sub for Vec2 {
+  type Output = Vec2;
+
+  fn sub(self, other: Vec2) -> Vec2 {
+    Vec2::new(self.x - other.x, self.y - other.y)
+  }
+}
+
+impl SubAssign for Vec2 {
+  fn sub_assign(&mut self, other: Vec2) {
+    *self = *self - other;
+  }
+}
+
+impl Mul<f64> for Vec2 {
+  type Output = Vec2;
+
+  fn mul(self, scale: f64) -> Vec2 {
+    Vec2::new(self.x * scale, self.y * scale)
+  }
+}
+
+impl Neg for Vec2 {
+  type Output = Vec2;
+
+  fn neg(self) -> Vec2 {
+    Vec2::new(-self.x, -self.y)
+  }
+}
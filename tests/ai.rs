@@ -0,0 +1,166 @@
+// 敵のAIが、プレイヤーとの距離に応じて状態を移っていくことを確かめる
+// Physicsは動かさないので、敵はその場から動かない
+use cave::ai::{Behavior, EnemyAi};
+use cave::assets::TextureHandle;
+use cave::components::*;
+use cave::game::{cave_tileset, FLOOR_TILE, WALL_TILE};
+use cave::movement::MovementTuning;
+use cave::tilemap::TileMap;
+use cave::time::DeltaTime;
+use cave::vector::Vec2;
+use specs::prelude::*;
+
+const BEHAVIOR: &str = "(
+  sight_range: 200.0,
+  attack_range: 40.0,
+  chase_speed: 1.0,
+  wander_speed: 0.5,
+  wander_radius: 64.0,
+  idle_time: 0.5,
+  memory: 1.0,
+  attack_time: 0.2,
+  attack_cooldown: 0.5,
+  repath_interval: 0.5,
+)";
+const TUNING: &str =
+  "(max_speed: 100.0, acceleration: 1000.0, deceleration: 1000.0, turn_rate: 0.0)";
+const STEP: f64 = 0.1;
+const ENEMY: Vec2 = Vec2::new(100.0, 100.0);
+// 見えないほど遠く、見えるけれど届かない所、飛びかかれる所
+const FAR: Vec2 = Vec2::new(600.0, 600.0);
+const NEAR: Vec2 = Vec2::new(250.0, 100.0);
+const CLOSE: Vec2 = Vec2::new(130.0, 100.0);
+
+struct Scene {
+  world: World,
+  enemy: Entity,
+  player: Entity,
+}
+
+impl Scene {
+  fn new() -> Self {
+    let mut world = World::new();
+    world.register::<InputControlled>();
+    world.register::<Position>();
+    world.register::<Ai>();
+    world.register::<Movement>();
+    world.add_resource(DeltaTime(STEP));
+    // 壁のない20x20タイルの部屋
+    let tileset = cave_tileset(TextureHandle::default());
+    world.add_resource(TileMap::new(20, 20, tileset, Some(FLOOR_TILE)));
+
+    let behavior = Behavior::parse(BEHAVIOR).unwrap();
+    let tuning = MovementTuning::parse(TUNING).unwrap();
+    let enemy = world
+      .create_entity()
+      .with(Position(ENEMY))
+      .with(Ai::new(behavior, ENEMY, 1))
+      .with(Movement::new(tuning))
+      .build();
+    let player = world
+      .create_entity()
+      .with(InputControlled)
+      .with(Position(FAR))
+      .build();
+    Self {
+      world,
+      enemy,
+      player,
+    }
+  }
+
+  fn move_player(&mut self, position: Vec2) {
+    self
+      .world
+      .write_storage::<Position>()
+      .get_mut(self.player)
+      .unwrap()
+      .0 = position;
+  }
+
+  fn tick(&mut self) {
+    EnemyAi.run_now(&self.world.res);
+  }
+
+  fn ai(&self) -> Ai {
+    self
+      .world
+      .read_storage::<Ai>()
+      .get(self.enemy)
+      .unwrap()
+      .clone()
+  }
+
+  fn desired(&self) -> Vec2 {
+    self
+      .world
+      .read_storage::<Movement>()
+      .get(self.enemy)
+      .unwrap()
+      .desired
+  }
+
+  // predicateを満たす状態になるまで進める。max_ticksのうちにならなければ失敗
+  fn tick_until<F: Fn(&AiState) -> bool>(&mut self, max_ticks: usize, predicate: F) {
+    for _ in 0..max_ticks {
+      self.tick();
+      if predicate(&self.ai().state) {
+        return;
+      }
+    }
+    panic!("the state is still {:?}", self.ai().state);
+  }
+}
+
+#[test]
+fn goes_from_idle_through_wander_chase_and_attack_to_the_cooldown() {
+  let mut scene = Scene::new();
+  scene.tick();
+  assert!(matches!(scene.ai().state, AiState::Idle { .. }));
+  assert_eq!(scene.desired(), Vec2::ZERO);
+
+  // 誰も見えなければ、立ち止まったあとにうろつき始める
+  scene.tick_until(10, |state| matches!(state, AiState::Wander { .. }));
+  assert_ne!(scene.desired(), Vec2::ZERO);
+
+  // 見えたら追いかける
+  scene.move_player(NEAR);
+  scene.tick();
+  assert!(matches!(scene.ai().state, AiState::Chase { .. }));
+  assert!(scene.desired().x > 0.0);
+
+  // 届く所まで来たら飛びかかる
+  scene.move_player(CLOSE);
+  scene.tick();
+  match scene.ai().state {
+    AiState::Attack { direction, .. } => assert_eq!(direction, Vec2::new(1.0, 0.0)),
+    state => panic!("expected an attack, got {:?}", state),
+  }
+
+  // 飛びかかり終わったら、届く所にいても次に飛びかかれるまでは追いかけるだけ
+  scene.tick_until(10, |state| !matches!(state, AiState::Attack { .. }));
+  assert!(matches!(scene.ai().state, AiState::Chase { .. }));
+  assert!(scene.ai().cooldown > 0.0);
+
+  scene.tick_until(10, |state| matches!(state, AiState::Attack { .. }));
+  assert!(scene.ai().cooldown <= 0.0);
+}
+
+#[test]
+fn a_player_behind_a_wall_is_not_seen() {
+  let mut scene = Scene::new();
+  {
+    let mut map = scene.world.write_resource::<TileMap>();
+    for y in 0..20 {
+      map.set(5, y, Some(WALL_TILE));
+    }
+  }
+  scene.move_player(NEAR);
+  for _ in 0..20 {
+    scene.tick();
+    assert!(!matches!(
+      scene.ai().state,
+      AiState::Chase { .. } | AiState::Attack { .. }
+    ));
+  }
+}
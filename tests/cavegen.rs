@@ -0,0 +1,67 @@
+// 洞窟の生成が、シードだけで決まって遊べる形になっていることを確かめる
+use cave::assets::AssetManager;
+use cave::cavegen::{self, Cave};
+use cave::game::{cave_settings, cave_tileset};
+use cave::tilemap::TileId;
+use std::collections::{HashSet, VecDeque};
+
+fn generate(seed: u64) -> Cave {
+  let tiles = AssetManager::default()
+    .load("assets/cave_tiles.png")
+    .unwrap();
+  cavegen::generate(&cave_settings(seed), cave_tileset(tiles))
+}
+
+fn tiles(cave: &Cave) -> Vec<Option<TileId>> {
+  let map = &cave.map;
+  (0..map.height)
+    .flat_map(|y| (0..map.width).map(move |x| map.get(x, y)))
+    .collect()
+}
+
+// startから上下左右に歩いて行けるタイル
+fn reachable(cave: &Cave, start: (i32, i32)) -> HashSet<(i32, i32)> {
+  let mut visited = HashSet::new();
+  let mut queue = VecDeque::new();
+  visited.insert(start);
+  queue.push_back(start);
+  while let Some((x, y)) = queue.pop_front() {
+    for &(dx, dy) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+      let next = (x + dx, y + dy);
+      if !cave.map.is_solid(next.0, next.1) && visited.insert(next) {
+        queue.push_back(next);
+      }
+    }
+  }
+  visited
+}
+
+#[test]
+fn the_same_seed_gives_the_same_cave() {
+  let (first, second) = (generate(42), generate(42));
+  assert_eq!(tiles(&first), tiles(&second));
+  assert_eq!(first.spawn, second.spawn);
+  assert_eq!(first.exit, second.exit);
+  assert_eq!(first.enemies, second.enemies);
+}
+
+#[test]
+fn a_different_seed_gives_a_different_cave() {
+  let (first, second) = (generate(1), generate(2));
+  assert_ne!(tiles(&first), tiles(&second));
+}
+
+#[test]
+fn the_exit_and_every_enemy_can_be_reached_from_the_spawn() {
+  for seed in 0..10 {
+    let cave = generate(seed);
+    assert!(!cave.map.is_solid(cave.spawn.0, cave.spawn.1));
+    assert_ne!(cave.spawn, cave.exit, "seed {}", seed);
+
+    let reachable = reachable(&cave, cave.spawn);
+    assert!(reachable.contains(&cave.exit), "seed {}", seed);
+    for enemy in &cave.enemies {
+      assert!(reachable.contains(enemy), "seed {}: {:?}", seed, enemy);
+    }
+  }
+}
@@ -0,0 +1,195 @@
+// Combatだけを小さなワールドで動かして、ダメージの受け方を確かめる
+use cave::combat::{Combat, CombatEvent, PlayerDefeated};
+use cave::components::*;
+use cave::time::DeltaTime;
+use cave::vector::Vec2;
+use sdl2::rect::Rect;
+use shrev::{EventChannel, ReaderId};
+use specs::prelude::*;
+
+const STEP: f64 = 0.1;
+const DAMAGE: Damage = Damage {
+  amount: 1,
+  knockback: 100.0,
+};
+
+struct Arena {
+  world: World,
+  reader: ReaderId<CombatEvent>,
+}
+
+impl Arena {
+  fn new() -> Self {
+    let mut world = World::new();
+    System::setup(&mut Combat, &mut world.res);
+    world.add_resource(DeltaTime(STEP));
+    let reader = world
+      .write_resource::<EventChannel<CombatEvent>>()
+      .register_reader();
+    Self { world, reader }
+  }
+
+  // 32x32の当たる範囲を持つ攻撃
+  fn attacker(&mut self, position: Vec2, team: Team) -> Entity {
+    self
+      .world
+      .create_entity()
+      .with(Position(position))
+      .with(Hitbox {
+        rect: Rect::new(-16, -16, 32, 32),
+        team,
+      })
+      .with(DAMAGE)
+      .build()
+  }
+
+  // 32x32の攻撃を受ける範囲を持つエンティティ
+  fn target(&mut self, position: Vec2, team: Team, health: Health) -> Entity {
+    self
+      .world
+      .create_entity()
+      .with(Position(position))
+      .with(Hurtbox {
+        rect: Rect::new(-16, -16, 32, 32),
+        team,
+      })
+      .with(health)
+      .with(Velocity::default())
+      .build()
+  }
+
+  fn run(&mut self) -> Vec<CombatEvent> {
+    Combat.run_now(&self.world.res);
+    self.world.maintain();
+    self
+      .world
+      .read_resource::<EventChannel<CombatEvent>>()
+      .read(&mut self.reader)
+      .copied()
+      .collect()
+  }
+
+  fn health(&self, entity: Entity) -> u32 {
+    self
+      .world
+      .read_storage::<Health>()
+      .get(entity)
+      .unwrap()
+      .current
+  }
+}
+
+#[test]
+fn an_overlapping_hitbox_deals_damage_and_knockback() {
+  let mut arena = Arena::new();
+  let attacker = arena.attacker(Vec2::new(0.0, 0.0), Team::Enemy);
+  let target = arena.target(Vec2::new(20.0, 0.0), Team::Player, Health::new(3, 0.5));
+
+  let events = arena.run();
+  assert_eq!(arena.health(target), 2);
+  assert_eq!(
+    events,
+    vec![CombatEvent::Damaged {
+      entity: target,
+      source: attacker,
+      amount: 1,
+    }]
+  );
+  // 攻撃した相手から離れる向きに弾き飛ばされる
+  let velocity = arena
+    .world
+    .read_storage::<Velocity>()
+    .get(target)
+    .unwrap()
+    .0;
+  assert_eq!(velocity, Vec2::new(100.0, 0.0));
+}
+
+#[test]
+fn the_same_team_is_not_damaged() {
+  let mut arena = Arena::new();
+  arena.attacker(Vec2::new(0.0, 0.0), Team::Enemy);
+  let target = arena.target(Vec2::new(20.0, 0.0), Team::Enemy, Health::new(3, 0.5));
+
+  assert!(arena.run().is_empty());
+  assert_eq!(arena.health(target), 3);
+}
+
+#[test]
+fn separated_boxes_do_not_damage() {
+  let mut arena = Arena::new();
+  arena.attacker(Vec2::new(0.0, 0.0), Team::Enemy);
+  // 辺が接しているだけなら当たっていない
+  let target = arena.target(Vec2::new(32.0, 0.0), Team::Player, Health::new(3, 0.5));
+
+  assert!(arena.run().is_empty());
+  assert_eq!(arena.health(target), 3);
+}
+
+#[test]
+fn only_one_of_several_overlapping_hitboxes_hits() {
+  let mut arena = Arena::new();
+  // 同じ陣営の攻撃は飛ばして、セルをまたいだ先の攻撃が当たる
+  arena.attacker(Vec2::new(60.0, 64.0), Team::Player);
+  let first = arena.attacker(Vec2::new(68.0, 64.0), Team::Enemy);
+  arena.attacker(Vec2::new(72.0, 64.0), Team::Enemy);
+  let target = arena.target(Vec2::new(64.0, 64.0), Team::Player, Health::new(3, 0.5));
+
+  let events = arena.run();
+  assert_eq!(arena.health(target), 2);
+  assert_eq!(
+    events,
+    vec![CombatEvent::Damaged {
+      entity: target,
+      source: first,
+      amount: 1,
+    }]
+  );
+}
+
+#[test]
+fn damage_is_ignored_while_invulnerable() {
+  let mut arena = Arena::new();
+  arena.attacker(Vec2::new(0.0, 0.0), Team::Enemy);
+  let target = arena.target(Vec2::new(20.0, 0.0), Team::Player, Health::new(5, 0.3));
+
+  arena.run();
+  assert_eq!(arena.health(target), 4);
+  // 無敵時間の0.3秒の間は、重なり続けていてもダメージを受けない
+  arena.run();
+  arena.run();
+  assert_eq!(arena.health(target), 4);
+  // 無敵時間が過ぎたら、また受ける
+  arena.run();
+  assert_eq!(arena.health(target), 3);
+}
+
+#[test]
+fn a_dead_enemy_is_deleted() {
+  let mut arena = Arena::new();
+  arena.attacker(Vec2::new(0.0, 0.0), Team::Player);
+  let target = arena.target(Vec2::new(20.0, 0.0), Team::Enemy, Health::new(1, 0.5));
+
+  let events = arena.run();
+  assert_eq!(events.last(), Some(&CombatEvent::Died { entity: target }));
+  assert!(!arena.world.is_alive(target));
+  assert!(!arena.world.read_resource::<PlayerDefeated>().0);
+}
+
+#[test]
+fn a_dead_player_is_kept_and_defeated() {
+  let mut arena = Arena::new();
+  arena.attacker(Vec2::new(0.0, 0.0), Team::Enemy);
+  let player = arena.target(Vec2::new(20.0, 0.0), Team::Player, Health::new(1, 0.5));
+  arena
+    .world
+    .write_storage()
+    .insert(player, InputControlled)
+    .unwrap();
+
+  let events = arena.run();
+  assert_eq!(events.last(), Some(&CombatEvent::Died { entity: player }));
+  assert!(arena.world.is_alive(player));
+  assert_eq!(arena.health(player), 0);
+  assert!(arena.world.read_resource::<PlayerDefeated>().0);
+}
@@ -0,0 +1,149 @@
+// 決まったワールドをソフトウェアレンダラーで描き、tests/golden/scene.pngと1ピクセルずつ比べる
+// 描き方を変えて絵が変わるのが正しいときは、UPDATE_GOLDEN=1をつけて実行すると期待する画像を書き直す
+use cave::assets::{AssetManager, Textures};
+use cave::camera::Camera;
+use cave::components::*;
+use cave::game::{cave_tileset, Game, EXIT_TILE, FLOOR_TILE, WALL_TILE};
+use cave::renderer;
+use cave::screenshot;
+use cave::text::{BitmapFont, Fonts};
+use cave::tilemap::TileMap;
+use sdl2::image::LoadSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::surface::Surface;
+use specs::prelude::*;
+use std::env;
+
+const GOLDEN: &str = "tests/golden/scene.png";
+const VIEWPORT: (u32, u32) = (128, 96);
+const BACKGROUND: Color = Color {
+  r: 20,
+  g: 12,
+  b: 28,
+  a: 255,
+};
+// SDLのバージョンによる半透明の合成の丸め方の違いは許す
+const TOLERANCE: u8 = 2;
+
+// 3x3タイルの部屋に、重なった2体のスプライトと文字を置く
+// 右端の1列にはタイルがないので背景色が見える
+fn scene() -> Game {
+  let mut game = Game::new();
+  renderer::SystemData::setup(&mut game.world.res);
+
+  let (tiles, seeker) = {
+    let mut assets = game.world.write_resource::<AssetManager>();
+    (
+      assets.load("assets/cave_tiles.png").unwrap(),
+      assets.load("assets/seeker.png").unwrap(),
+    )
+  };
+  let mut map = TileMap::new(3, 3, cave_tileset(tiles), Some(FLOOR_TILE));
+  for x in 0..3 {
+    map.set(x, 0, Some(WALL_TILE));
+  }
+  map.set(2, 2, Some(EXIT_TILE));
+  game.world.add_resource(map);
+
+  let mut camera = Camera::default();
+  camera.viewport = VIEWPORT;
+  camera.look_at(Point::new(64, 48));
+  game.world.add_resource(camera);
+
+  game
+    .world
+    .create_entity()
+    .with(Position(Point::new(40, 56).into()))
+    .with(Sprite {
+      spritesheet: seeker,
+      region: Rect::new(32, 0, 32, 32),
+      flip: Flip::default(),
+    })
+    .build();
+  // 下にあるほど手前に描くので、こちらが上に重なる。左右反転も確かめる
+  game
+    .world
+    .create_entity()
+    .with(Position(Point::new(64, 64).into()))
+    .with(Sprite {
+      spritesheet: seeker,
+      region: Rect::new(0, 64, 32, 32),
+      flip: Flip {
+        horizontal: true,
+        vertical: false,
+      },
+    })
+    .build();
+  game
+    .world
+    .create_entity()
+    .with(Position(Point::new(4, 4).into()))
+    .with(Text {
+      content: "HP 5".to_string(),
+      color: Color::RGB(255, 255, 255),
+      size: 8,
+    })
+    .build();
+  game
+}
+
+#[test]
+fn renders_the_scene_like_the_golden_image() {
+  let game = scene();
+  let font = BitmapFont::load(
+    "assets/fonts/font5x7.ron",
+    &mut game.world.write_resource::<AssetManager>(),
+  )
+  .unwrap();
+  let mut fonts = Fonts::new(font);
+
+  let surface = Surface::new(VIEWPORT.0, VIEWPORT.1, PixelFormatEnum::RGBA32).unwrap();
+  let mut canvas = surface.into_canvas().unwrap();
+  let texture_creator = canvas.texture_creator();
+  let mut textures = Textures::new(&texture_creator);
+  textures
+    .sync(&game.world.read_resource::<AssetManager>())
+    .unwrap();
+  renderer::render(
+    &mut canvas,
+    BACKGROUND,
+    &mut textures,
+    &mut fonts,
+    1.0,
+    game.world.system_data(),
+  )
+  .unwrap();
+
+  if env::var_os("UPDATE_GOLDEN").is_some() {
+    screenshot::save_png(&canvas, GOLDEN).unwrap();
+    return;
+  }
+
+  let actual = canvas.read_pixels(None, PixelFormatEnum::RGBA32).unwrap();
+  let golden = Surface::from_file(GOLDEN)
+    .and_then(|surface| surface.convert_format(PixelFormatEnum::RGBA32))
+    .unwrap();
+  assert_eq!((golden.width(), golden.height()), VIEWPORT);
+
+  let pitch = golden.pitch() as usize;
+  let width = VIEWPORT.0 as usize;
+  let expected = golden.without_lock().unwrap();
+  let mut mismatches = Vec::new();
+  for y in 0..VIEWPORT.1 as usize {
+    for x in 0..width {
+      let a = &actual[(y * width + x) * 4..][..4];
+      let e = &expected[y * pitch + x * 4..][..4];
+      if a.iter().zip(e).any(|(a, e)| a.abs_diff(*e) > TOLERANCE) {
+        mismatches.push(((x, y), a.to_vec(), e.to_vec()));
+      }
+    }
+  }
+  assert!(
+    mismatches.is_empty(),
+    "{} pixels differ from {}, first: {:?}",
+    mismatches.len(),
+    GOLDEN,
+    mismatches.first()
+  );
+}
@@ -0,0 +1,206 @@
+// SDLのウィンドウを作らずに、台本どおりの入力でシミュレーションを進めて結果を確かめる
+use cave::ai::Behavior;
+use cave::animation::AnimationDef;
+use cave::camera::Camera;
+use cave::components::*;
+use cave::game::{create_enemy, create_health_display, create_player, Game};
+use cave::headless::{self, Script};
+use cave::input::{Action, InputState};
+use cave::movement::MovementTuning;
+use cave::scene::{Scene, SceneStack, Transition};
+use cave::vector::Vec2;
+use sdl2::rect::{Point, Rect};
+use specs::prelude::*;
+
+// assets/scripts/walk_square.txtと同じく、右、下、左、上に30ステップずつ歩く
+const WALK_SQUARE: &str = "
+0 press MoveRight
+30 release MoveRight
+30 press MoveDown
+60 release MoveDown
+60 press MoveLeft
+90 release MoveLeft
+90 press MoveUp
+120 release MoveUp
+";
+
+// アセットの調整で結果が変わらないように、動きの設定はここで決めておく
+// すぐに最高速度になってすぐに止まるので、4辺を同じ長さだけ歩く
+const TUNING: &str = "(max_speed: 300.0, acceleration: 1e9, deceleration: 1e9, turn_rate: 0.0)";
+
+fn new_game() -> (Game, Entity) {
+  let mut game = Game::new();
+  let animation = AnimationDef::load("assets/seeker.ron")
+    .and_then(|def| def.movement_animation(&mut game.world.write_resource()))
+    .unwrap();
+  let tuning = MovementTuning::parse(TUNING).unwrap();
+  let player = create_player(&mut game.world, animation, tuning, Point::new(0, 0));
+  (game, player)
+}
+
+fn state(game: &Game, player: Entity) -> (Vec2, Vec2, Rect) {
+  let position = game.world.read_storage::<Position>().get(player).unwrap().0;
+  let velocity = game.world.read_storage::<Velocity>().get(player).unwrap().0;
+  let region = game
+    .world
+    .read_storage::<Sprite>()
+    .get(player)
+    .unwrap()
+    .region;
+  (position, velocity, region)
+}
+
+#[test]
+fn walking_right_moves_and_animates_the_player() {
+  let (mut game, player) = new_game();
+  headless::run(&mut game, 30, &Script::parse(WALK_SQUARE).unwrap());
+
+  let (position, velocity, region) = state(&game, player);
+  // 1秒に300ピクセルの速さで30ステップ(0.5秒)歩く
+  assert!((position.x - 150.0).abs() < 1e-6, "moved to {:?}", position);
+  assert_eq!(position.y, 0.0);
+  assert!(velocity.x > 0.0);
+  // seeker.ronの右向きの歩行クリップは3行目に並んでいる
+  assert_eq!(region.y(), 64);
+}
+
+#[test]
+fn walking_a_square_returns_to_the_start_and_stops() {
+  let (mut game, player) = new_game();
+  headless::run(&mut game, 240, &Script::parse(WALK_SQUARE).unwrap());
+
+  let (position, velocity, region) = state(&game, player);
+  assert_eq!(velocity, Vec2::ZERO);
+  assert!(position.length() < 1e-6, "ended at {:?}", position);
+  // 最後に上へ歩いたので、上向きの待機フレームで止まる
+  assert_eq!(region, Rect::new(32, 96, 32, 32));
+}
+
+#[test]
+fn the_same_script_gives_the_same_result() {
+  let script = Script::parse(WALK_SQUARE).unwrap();
+  let (mut first, first_player) = new_game();
+  let (mut second, second_player) = new_game();
+  headless::run(&mut first, 100, &script);
+  headless::run(&mut second, 100, &script);
+
+  assert_eq!(state(&first, first_player), state(&second, second_player));
+}
+
+#[test]
+fn the_health_display_follows_the_player_health() {
+  let (mut game, player) = new_game();
+  let display = create_health_display(&mut game.world, player);
+  let text = |game: &Game| {
+    game
+      .world
+      .read_storage::<Text>()
+      .get(display)
+      .unwrap()
+      .content
+      .clone()
+  };
+  assert_eq!(text(&game), "HP 5/5");
+
+  game
+    .world
+    .write_storage::<Health>()
+    .get_mut(player)
+    .unwrap()
+    .current = 2;
+  game.tick();
+  assert_eq!(text(&game), "HP 2/5");
+}
+
+#[test]
+fn attacking_damages_and_pushes_back_an_enemy_in_front() {
+  let (mut game, _) = new_game();
+  let animation = AnimationDef::load("assets/seeker.ron")
+    .and_then(|def| def.movement_animation(&mut game.world.write_resource()))
+    .unwrap();
+  let tuning = MovementTuning::load("assets/enemy_movement.ron").unwrap();
+  let behavior = Behavior::load("assets/enemy_behavior.ron").unwrap();
+  // プレイヤーは最初は下を向いている
+  let enemy = create_enemy(
+    &mut game.world,
+    animation,
+    tuning,
+    behavior,
+    Point::new(0, 40),
+    1,
+  );
+  let slashes = |game: &Game| {
+    (&game.world.read_storage::<Hitbox>())
+      .join()
+      .filter(|hitbox| hitbox.team == Team::Player)
+      .count()
+  };
+
+  headless::run(&mut game, 1, &Script::parse("0 press Attack").unwrap());
+  assert_eq!(slashes(&game), 1);
+  let health = game
+    .world
+    .read_storage::<Health>()
+    .get(enemy)
+    .unwrap()
+    .current;
+  assert_eq!(health, 2);
+  let velocity = game.world.read_storage::<Velocity>().get(enemy).unwrap().0;
+  assert!(velocity.y > 0.0, "pushed at {:?}", velocity);
+
+  // 弾き飛ばされている間は、プレイヤーへ向かって歩こうとしても速度が変わらない
+  headless::run(&mut game, 5, &Script::parse("").unwrap());
+  let pushed = game.world.read_storage::<Velocity>().get(enemy).unwrap().0;
+  assert_eq!(pushed, velocity);
+  let position = game.world.read_storage::<Position>().get(enemy).unwrap().0;
+  assert!(position.y > 60.0, "pushed to {:?}", position);
+
+  // 攻撃の当たる範囲はすぐに消える
+  headless::run(&mut game, 25, &Script::parse("").unwrap());
+  assert_eq!(slashes(&game), 0);
+}
+
+#[test]
+fn pausing_mid_movement_freezes_the_drawn_position() {
+  let (mut game, player) = new_game();
+  game.world.write_resource::<Camera>().target = Some(player);
+  let mut scenes = SceneStack::new(Scene::Playing);
+
+  game
+    .world
+    .write_resource::<InputState>()
+    .press(Action::MoveRight);
+  for _ in 0..10 {
+    scenes.update(&mut game);
+  }
+  game
+    .world
+    .write_resource::<InputState>()
+    .press(Action::Pause);
+  assert_eq!(
+    scenes.update(&mut game),
+    Transition::Push(Scene::Paused { selected: 0 })
+  );
+
+  // 止まっている間は、どのフレームの補間係数で描いても同じ所に描く
+  let drawn = |game: &Game, alpha: f64| {
+    let position = game.world.read_storage::<Position>().get(player).unwrap().0;
+    let previous = game
+      .world
+      .read_storage::<PreviousPosition>()
+      .get(player)
+      .unwrap()
+      .0;
+    let camera = game.world.read_resource::<Camera>();
+    (
+      previous.lerp(position, alpha).to_point(),
+      camera.view(alpha),
+    )
+  };
+  let first = drawn(&game, 0.0);
+  assert!(first.0.x() > 0, "did not move: {:?}", first);
+  for frame in 1..=10 {
+    scenes.update(&mut game);
+    assert_eq!(drawn(&game, f64::from(frame) / 10.0), first);
+  }
+}
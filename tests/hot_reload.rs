@@ -0,0 +1,104 @@
+// 一時ディレクトリに書いたファイルを見張り、書き換えたら読み込み直されることを確かめる
+use cave::animation::AnimationDef;
+use cave::components::*;
+use cave::game::{create_player, Game};
+use cave::hot_reload::{reload_animation, PollingWatcher};
+use cave::movement::MovementTuning;
+use sdl2::rect::{Point, Rect};
+use specs::prelude::*;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
+
+const TUNING: &str = "(max_speed: 300.0, acceleration: 1e9, deceleration: 1e9, turn_rate: 0.0)";
+
+// seeker.ronの下向きの待機フレーム。書き換えるときはこれを右隣のフレームにする
+const IDLE_DOWN: &str = "frames: [(region: (32, 0, 32, 32), duration: 0.0)]";
+const NEW_IDLE_DOWN: &str = "frames: [(region: (64, 0, 32, 32), duration: 0.0)]";
+
+// テストごとに別の一時ディレクトリを使い、終わったら消す
+struct TempDir(PathBuf);
+
+impl TempDir {
+  fn new(name: &str) -> Self {
+    let path = std::env::temp_dir().join(format!("cave-{}-{}", name, process::id()));
+    fs::create_dir_all(&path).unwrap();
+    TempDir(path)
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+// 更新時刻の分解能が粗いファイルシステムでも変更に気づけるように、更新時刻をずらしておく
+fn set_old_mtime(path: &Path) {
+  let old = SystemTime::now() - Duration::from_secs(60);
+  File::options()
+    .write(true)
+    .open(path)
+    .unwrap()
+    .set_modified(old)
+    .unwrap();
+}
+
+fn sprite(game: &Game, entity: Entity) -> Sprite {
+  game
+    .world
+    .read_storage::<Sprite>()
+    .get(entity)
+    .unwrap()
+    .clone()
+}
+
+#[test]
+fn rewriting_an_animation_reloads_it_with_the_same_spritesheet() {
+  let dir = TempDir::new("hot-reload");
+  let path = dir.0.join("seeker.ron");
+  let source = fs::read_to_string("assets/seeker.ron").unwrap();
+  assert!(source.contains(IDLE_DOWN));
+  fs::write(&path, &source).unwrap();
+  set_old_mtime(&path);
+
+  let mut game = Game::new();
+  let animation = AnimationDef::load(&path)
+    .and_then(|def| def.movement_animation(&mut game.world.write_resource()))
+    .unwrap();
+  let tuning = MovementTuning::parse(TUNING).unwrap();
+  let player = create_player(&mut game.world, animation, tuning, Point::new(0, 0));
+  game
+    .world
+    .write_storage()
+    .insert(player, AnimationSource(path.clone()))
+    .unwrap();
+
+  let mut watcher = PollingWatcher::new();
+  watcher.watch(&path);
+  assert!(watcher.poll().is_empty());
+
+  game.tick();
+  let before = sprite(&game, player);
+  assert_eq!(before.region, Rect::new(32, 0, 32, 32));
+
+  fs::write(&path, source.replace(IDLE_DOWN, NEW_IDLE_DOWN)).unwrap();
+  assert_eq!(watcher.poll(), vec![path.clone()]);
+  // 一度知らせた変更は、次のpollでは知らせない
+  assert!(watcher.poll().is_empty());
+  assert_eq!(reload_animation(&mut game.world, &path), Ok(true));
+
+  game.tick();
+  let after = sprite(&game, player);
+  assert_eq!(after.region, Rect::new(64, 0, 32, 32));
+  // 同じスプライトシートは読み込み直さず、同じハンドルを使い続ける
+  assert_eq!(after.spritesheet, before.spritesheet);
+}
+
+#[test]
+fn reloading_a_file_nobody_uses_does_nothing() {
+  let mut game = Game::new();
+  let path = PathBuf::from("assets/seeker.ron");
+  assert_eq!(reload_animation(&mut game.world, &path), Ok(false));
+}
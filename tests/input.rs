@@ -0,0 +1,47 @@
+use cave::input::{Action, InputSource, InputState};
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+
+#[test]
+fn action_stays_held_until_every_key_is_released() {
+  let mut input = InputState::default();
+  input.press_from(InputSource::Key(Keycode::Up), Action::MoveUp);
+  input.press_from(InputSource::Key(Keycode::W), Action::MoveUp);
+  assert!(input.just_pressed(Action::MoveUp));
+  input.end_tick();
+
+  // 片方を離しても、もう片方が押されている間は押したまま
+  input.release_from(InputSource::Key(Keycode::Up), Action::MoveUp);
+  assert!(input.is_held(Action::MoveUp));
+  assert!(!input.just_released(Action::MoveUp));
+
+  input.release_from(InputSource::Key(Keycode::W), Action::MoveUp);
+  assert!(!input.is_held(Action::MoveUp));
+  assert!(input.just_released(Action::MoveUp));
+}
+
+#[test]
+fn pressing_again_from_another_key_is_not_a_new_press() {
+  let mut input = InputState::default();
+  input.press_from(InputSource::Key(Keycode::Space), Action::Attack);
+  input.end_tick();
+  input.press_from(InputSource::Key(Keycode::Z), Action::Attack);
+  assert!(!input.just_pressed(Action::Attack));
+}
+
+#[test]
+fn releasing_a_pad_keeps_actions_held_by_the_keyboard() {
+  let mut input = InputState::default();
+  input.press_from(InputSource::Key(Keycode::Left), Action::MoveLeft);
+  input.press_from(InputSource::Button(0, Button::DPadLeft), Action::MoveLeft);
+  input.press_from(InputSource::Button(0, Button::A), Action::Attack);
+  input.press_from(InputSource::Button(1, Button::A), Action::Attack);
+
+  input.release_sources(|source| matches!(source, InputSource::Button(0, _)));
+  assert!(input.is_held(Action::MoveLeft));
+  assert!(input.is_held(Action::Attack));
+
+  input.release_sources(|source| matches!(source, InputSource::Button(1, _)));
+  assert!(input.is_held(Action::MoveLeft));
+  assert!(!input.is_held(Action::Attack));
+}
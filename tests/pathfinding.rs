@@ -0,0 +1,119 @@
+// A*の経路探索と見通しの判定を、文字で描いた小さなマップで確かめる
+use cave::assets::TextureHandle;
+use cave::game::{cave_tileset, FLOOR_TILE, WALL_TILE};
+use cave::pathfinding::{find_path, line_of_sight, Tile};
+use cave::tilemap::TileMap;
+use cave::vector::Vec2;
+
+// 真ん中に縦の壁がある部屋
+const WALL: &str = "
+.......
+...#...
+...#...
+...#...
+.......
+";
+
+// 真ん中に柱が1本だけある部屋
+const PILLAR: &str = "
+...
+.#.
+...
+";
+
+// 斜めに並んだ2つの壁の間は通れない
+const DIAGONAL: &str = "
+.#
+#.
+";
+
+// 壁で囲まれた1マス
+const ENCLOSED: &str = "
+.....
+.###.
+.#.#.
+.###.
+.....
+";
+
+// 真ん中に壁が1つだけある
+const BLOCK: &str = "
+.....
+..#..
+.....
+";
+
+// '#'が壁、それ以外が床。空の行は無視する
+fn map(source: &str) -> TileMap {
+  let rows: Vec<&str> = source.lines().filter(|row| !row.is_empty()).collect();
+  let tileset = cave_tileset(TextureHandle::default());
+  let mut map = TileMap::new(rows[0].len() as i32, rows.len() as i32, tileset, None);
+  for (y, row) in rows.iter().enumerate() {
+    for (x, c) in row.chars().enumerate() {
+      let tile = if c == '#' { WALL_TILE } else { FLOOR_TILE };
+      map.set(x as i32, y as i32, Some(tile));
+    }
+  }
+  map
+}
+
+// タイルの中心のワールド座標
+fn center(x: i32, y: i32) -> Vec2 {
+  Vec2::new(f64::from(x * 32 + 16), f64::from(y * 32 + 16))
+}
+
+// 1マスずつ、壁を通らず、壁の角をかすめずに進んでいること
+fn assert_walkable(map: &TileMap, start: Tile, path: &[Tile]) {
+  let mut from = start;
+  for &to in path {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    assert!(dx.abs() <= 1 && dy.abs() <= 1, "{:?} -> {:?}", from, to);
+    assert!(!map.is_solid(to.0, to.1), "{:?} is a wall", to);
+    if dx != 0 && dy != 0 {
+      assert!(!map.is_solid(from.0 + dx, from.1), "{:?} -> {:?}", from, to);
+      assert!(!map.is_solid(from.0, from.1 + dy), "{:?} -> {:?}", from, to);
+    }
+    from = to;
+  }
+}
+
+#[test]
+fn finds_a_path_around_a_wall() {
+  let map = map(WALL);
+  let path = find_path(&map, (1, 2), (5, 2)).unwrap();
+  assert_eq!(path.last(), Some(&(5, 2)));
+  assert_walkable(&map, (1, 2), &path);
+  // 壁の上か下の端を回り込む。まっすぐなら4マス
+  assert!(path.iter().any(|&(_, y)| y == 0 || y == 4));
+  assert_eq!(path.len(), 6);
+}
+
+#[test]
+fn does_not_cut_corners() {
+  let pillar = map(PILLAR);
+  let path = find_path(&pillar, (0, 0), (2, 2)).unwrap();
+  assert_walkable(&pillar, (0, 0), &path);
+  // 真ん中の壁の角をかすめられないので、斜めには進めない
+  assert_eq!(path.len(), 4);
+
+  let diagonal = map(DIAGONAL);
+  assert_eq!(find_path(&diagonal, (0, 0), (1, 1)), None);
+}
+
+#[test]
+fn an_unreachable_goal_has_no_path() {
+  let map = map(ENCLOSED);
+  assert_eq!(find_path(&map, (0, 0), (2, 2)), None);
+  // 壁そのものも目指せない
+  assert_eq!(find_path(&map, (0, 0), (1, 1)), None);
+}
+
+#[test]
+fn walls_block_the_line_of_sight() {
+  let map = map(BLOCK);
+  assert!(!line_of_sight(&map, center(0, 1), center(4, 1)));
+  assert!(line_of_sight(&map, center(0, 0), center(4, 0)));
+  assert!(line_of_sight(&map, center(0, 2), center(4, 2)));
+  // 壁の手前までなら見える
+  assert!(line_of_sight(&map, center(0, 1), center(1, 1)));
+}
@@ -0,0 +1,32 @@
+// SpatialHashに入れた当たり判定を動かしたときに、問い合わせの結果が追いつくかを確かめる
+use cave::spatial::SpatialHash;
+use sdl2::rect::Rect;
+use specs::prelude::*;
+
+#[test]
+fn updated_entries_are_found_at_their_new_position() {
+  let mut world = World::new();
+  let entity = world.create_entity().build();
+
+  let mut hash = SpatialHash::new(32);
+  hash.insert(entity, Rect::new(0, 0, 16, 16));
+  // 別のセルへ動かす
+  hash.update(entity, Rect::new(100, 100, 16, 16));
+
+  assert!(hash.query_rect(Rect::new(0, 0, 16, 16)).is_empty());
+  assert_eq!(hash.query_rect(Rect::new(90, 90, 16, 16)), vec![entity]);
+  assert_eq!(hash.rect(entity), Some(Rect::new(100, 100, 16, 16)));
+}
+
+#[test]
+fn updating_within_a_cell_keeps_a_single_entry() {
+  let mut world = World::new();
+  let entity = world.create_entity().build();
+
+  let mut hash = SpatialHash::new(32);
+  hash.insert(entity, Rect::new(0, 0, 8, 8));
+  hash.update(entity, Rect::new(4, 4, 8, 8));
+
+  assert_eq!(hash.query_rect(Rect::new(0, 0, 32, 32)), vec![entity]);
+  assert_eq!(hash.len(), 1);
+}
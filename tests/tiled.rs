@@ -0,0 +1,146 @@
+// Tiledで作ったassets/maps/roomを、tmxとtmjのどちらから読んでも同じマップになることを確かめる
+use cave::assets::AssetManager;
+use cave::tiled::TiledMap;
+use sdl2::rect::{Point, Rect};
+use std::fs;
+use std::process;
+
+// cave_tiles.pngの並び
+const FLOOR: usize = 0;
+const WALL: usize = 1;
+const EXIT: usize = 2;
+
+fn load(path: &str) -> Result<TiledMap, String> {
+  TiledMap::load(path, &mut AssetManager::default())
+}
+
+fn check_room(tiled: &TiledMap) {
+  let map = &tiled.map;
+  assert_eq!((map.width, map.height), (20, 15));
+  assert_eq!(map.pixel_size(), (640, 480));
+
+  // 外周は壁で、中に小さな壁が2つと出口がある
+  assert_eq!(map.get(0, 0), Some(WALL));
+  assert_eq!(map.get(19, 14), Some(WALL));
+  assert_eq!(map.get(1, 1), Some(FLOOR));
+  assert_eq!(map.get(6, 5), Some(WALL));
+  assert_eq!(map.get(7, 5), Some(WALL));
+  assert_eq!(map.get(12, 9), Some(WALL));
+  assert_eq!(map.get(17, 12), Some(EXIT));
+  assert!(map.is_solid(6, 5));
+  assert!(!map.is_solid(17, 12));
+
+  assert_eq!(tiled.player_spawn(), Some(Point::new(80, 80)));
+  assert_eq!(tiled.enemy_spawns(), vec![Point::new(496, 112)]);
+
+  let names: Vec<&str> = tiled.objects.iter().map(|o| o.name.as_str()).collect();
+  assert_eq!(names, ["player", "boulder", "goal", "lurker"]);
+  // タイルオブジェクトの座標は左下なので、上に1タイル分ずらした所に置かれる
+  let boulder = &tiled.objects[1];
+  assert_eq!(boulder.kind, "prop");
+  assert_eq!(boulder.rect, Rect::new(320, 192, 32, 32));
+  assert_eq!(boulder.tile, Some(WALL));
+  assert!(boulder.solid);
+  let goal = &tiled.objects[2];
+  assert_eq!(goal.kind, "trigger");
+  assert_eq!(goal.rect, Rect::new(544, 384, 32, 32));
+  assert_eq!(goal.tile, None);
+  assert!(!goal.solid);
+}
+
+#[test]
+fn loads_the_room_from_tmx() {
+  check_room(&load("assets/maps/room.tmx").unwrap());
+}
+
+#[test]
+fn loads_the_room_from_tmj() {
+  check_room(&load("assets/maps/room.tmj").unwrap());
+}
+
+#[test]
+fn rejects_an_unknown_extension() {
+  assert!(load("assets/maps/cave_tiles.tsx").is_err());
+}
+
+#[test]
+fn rejects_a_map_with_a_bad_size() {
+  let dir = std::env::temp_dir().join(format!("cave-tiled-{}", process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  let source = fs::read_to_string("assets/maps/room.tmj").unwrap();
+
+  let sizes = [(0, 15), (20, -1), (100_000, 100_000)];
+  for (i, &(width, height)) in sizes.iter().enumerate() {
+    let path = dir.join(format!("bad{}.tmj", i));
+    let broken = source.replacen("\"width\": 20", &format!("\"width\": {}", width), 1);
+    let broken = broken.replacen("\"height\": 15", &format!("\"height\": {}", height), 1);
+    fs::write(&path, broken).unwrap();
+
+    let error = load(path.to_str().unwrap()).unwrap_err();
+    assert!(error.contains("map size"), "{}", error);
+  }
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+// room.tmjの一部を書き換えて読み込む。画像はassetsにあるものをそのまま使う
+fn load_edited(name: &str, edits: &[(&str, &str)]) -> Result<TiledMap, String> {
+  let dir = std::env::temp_dir().join(format!("cave-tiled-{}-{}", name, process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  let image = fs::canonicalize("assets/cave_tiles.png").unwrap();
+  let mut source = fs::read_to_string("assets/maps/room.tmj")
+    .unwrap()
+    .replace("../cave_tiles.png", image.to_str().unwrap());
+  for &(from, to) in edits {
+    assert!(source.contains(from), "{:?} is not in room.tmj", from);
+    source = source.replacen(from, to, 1);
+  }
+  let path = dir.join(format!("{}.tmj", name));
+  fs::write(&path, source).unwrap();
+  let result = load(path.to_str().unwrap());
+  fs::remove_dir_all(&dir).unwrap();
+  result
+}
+
+#[test]
+fn rejects_a_zero_tile_size() {
+  // マップではなく、タイルセットのtileheight
+  let tileset = "\"tileheight\": 32,\n   \"tilecount\"";
+  let edits = [(tileset, "\"tileheight\": 0,\n   \"tilecount\"")];
+  let error = load_edited("zero-tile-size", &edits).unwrap_err();
+  assert!(error.contains("tile size"), "{}", error);
+}
+
+#[test]
+fn flips_tile_objects() {
+  // 0x80000002: 左右に反転した壁のタイル
+  let map = load_edited("flipped", &[("\"gid\": 2", "\"gid\": 2147483650")]).unwrap();
+  let boulder = &map.objects[1];
+  assert_eq!(boulder.tile, Some(WALL));
+  assert!(boulder.flip.horizontal);
+  assert!(!boulder.flip.vertical);
+  assert!(!map.objects[0].flip.horizontal);
+}
+
+#[test]
+fn rejects_what_cannot_be_represented() {
+  // 1073741826(0x40000002)は上下に反転した壁のタイル
+  let cases = [
+    (
+      "layer-flip",
+      "\"data\": [\n    2,",
+      "\"data\": [\n    1073741826,",
+      "flipped",
+    ),
+    ("rotation", "\"rotation\": 0", "\"rotation\": 90", "rotated"),
+    (
+      "group",
+      "\"type\": \"objectgroup\"",
+      "\"type\": \"group\"",
+      "group",
+    ),
+  ];
+  for &(name, from, to, message) in &cases {
+    let error = load_edited(name, &[(from, to)]).unwrap_err();
+    assert!(error.contains(message), "{}", error);
+  }
+}